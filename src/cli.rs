@@ -23,14 +23,35 @@ impl From<bool> for StatBehavior {
 pub struct AppConfig {
     pub file_path: String,
     pub behavior: StatBehavior,
+    pub quiet: bool,
+    pub timings: Option<String>,
+    pub dry_run: bool,
+    pub watch: bool,
+    pub stream: Option<String>,
+    pub resume: bool,
+    pub journal: String,
+    pub strict: bool,
 }
 impl Default for AppConfig {
     fn default() -> AppConfig {
         let app = build_cli();
         let matches = app.get_matches();
+        let file_path = matches.value_of("FILE").unwrap().to_string();
+        let journal = match matches.value_of("journal") {
+            Option::Some(dir) => dir.to_string(),
+            Option::None => format!("{}.journal", &file_path),
+        };
         AppConfig {
-            file_path: matches.value_of("FILE").unwrap().to_string(),
+            file_path,
             behavior: StatBehavior::from(matches.is_present("accum")),
+            quiet: matches.is_present("quiet"),
+            timings: matches.value_of("timings").map(|x| x.to_string()),
+            dry_run: matches.is_present("dry-run"),
+            watch: matches.is_present("watch"),
+            stream: matches.value_of("stream").map(|x| x.to_string()),
+            resume: matches.is_present("resume"),
+            journal,
+            strict: matches.is_present("strict"),
         }
     }
 }
@@ -57,6 +78,60 @@ fn build_cli() -> App<'static, 'static> {
                 .takes_value(false)
                 .help("return accumulations instead of raw probability"),
         )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .takes_value(false)
+                .help("do not report progress on stderr"),
+        )
+        .arg(
+            Arg::with_name("timings")
+                .long("timings")
+                .takes_value(true)
+                .value_name("PATH")
+                .help("write the time & memory each run used to a CSV"),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .takes_value(false)
+                .help("print the commands which would be ran, without running them"),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .long("watch")
+                .takes_value(false)
+                .conflicts_with("dry-run")
+                .help("re-run the runs which changed whenever the config or a program is saved"),
+        )
+        .arg(
+            Arg::with_name("stream")
+                .long("stream")
+                .takes_value(true)
+                .value_name("PATH")
+                .help("append every run to a JSON lines file as soon as it finishes"),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .takes_value(false)
+                .conflicts_with("watch")
+                .help("skip runs the last (interrupted) batch completed, if they're unchanged"),
+        )
+        .arg(
+            Arg::with_name("journal")
+                .long("journal")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("where completed runs are journaled for --resume [default: <FILE>.journal]"),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .takes_value(false)
+                .help("fail runs whose output doesn't pass the sanity checks, instead of warning"),
+        )
 }
 
 fn validate_input_file(arg: String) -> Result<(), String> {
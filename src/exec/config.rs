@@ -1,104 +1,421 @@
 use std::collections::BTreeMap;
+use std::env::temp_dir;
+use std::fs::read;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::super::serde::Deserialize;
+use super::super::unmarshal::TrollConfig;
 
-use super::runs::TrollRun;
+use super::hash::fnv1a;
+use super::runs::{ProgramInfo, ProgramSource, ScratchProgram, TrollRun};
 
 /// RunKind states what file we will execute
 #[derive(Clone, Deserialize, Debug, PartialEq, Eq, Hash)]
 #[serde(untagged)]
 pub enum RunKind {
     Trivial(String),
-    ComplexRun(ComplexRun),
+    ComplexRun(Box<ComplexRun>),
 }
 impl RunKind {
-    pub fn into_exec(&self, name: &String, troll_path: &Option<String>) -> TrollRun {
+    pub fn into_exec(&self, name: &String, troll: &TrollConfig, strict: bool) -> TrollRun {
         // build the initial command structure
-        let mut cmd = match troll_path {
-            &Option::None => Command::new("troll"),
-            &Option::Some(ref path) => Command::new(path),
+        let bin = self.bin();
+        let mut cmd = Command::new(executable(troll, &bin));
+        let scratch = self.scratch(name, troll);
+        match scratch {
+            Option::None => self.add_args(&mut cmd, None),
+            Option::Some(ref scratch) => self.add_args(&mut cmd, Some(&scratch.path)),
         };
-        self.add_args(&mut cmd);
+        let env = self.env(troll);
+        let cwd = self.cwd(troll);
+        let env_clear = self.env_clear(troll);
+        if env_clear {
+            cmd.env_clear();
+        }
+        cmd.envs(&env);
+        if let Option::Some(ref cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+        let mmap = self.mmap_override(troll);
+        if mmap {
+            add_mmap(&mut cmd);
+        }
+        let max_memory = self.max_memory(troll);
+        if let Option::Some(mib) = max_memory {
+            limit_memory(&mut cmd, mib);
+        }
         TrollRun {
             cmd,
             name: name.to_string(),
+            label: self.label(),
+            program: ProgramInfo {
+                env,
+                cwd,
+                bin,
+                ..self.program()
+            },
+            samples: self.samples(),
+            max_memory,
+            mmap,
+            env_clear,
+            scratch,
+            strict,
+        }
+    }
+
+    /// fingerprint changes whenever re-running would give a different result.
+    ///
+    /// It covers the run's entry, the `[troll]` section, and the program's contents.
+    pub fn fingerprint(&self, troll: &TrollConfig) -> u64 {
+        let mut bytes = format!("{:?}\n{:?}\n", self, troll).into_bytes();
+        for path in self.watched_files(troll) {
+            match read(&path) {
+                Ok(contents) => bytes.extend(contents),
+                Err(_) => bytes.extend(b"\0missing\0"),
+            };
+        }
+        fnv1a(&bytes)
+    }
+
+    /// the files the run reads, changing one of them changes the result
+    pub fn watched_files(&self, troll: &TrollConfig) -> Vec<PathBuf> {
+        let path = match self {
+            RunKind::Trivial(path) => path,
+            RunKind::ComplexRun(complex) => match (&complex.path, &complex.source) {
+                (Option::Some(path), Option::None) => path,
+                _ => return Vec::new(),
+            },
+        };
+        vec![PathBuf::from(resolve(&self.cwd(troll), path))]
+    }
+
+    /// programs given inline, or with substitutions, have to be written out
+    /// before troll can run them
+    fn scratch(&self, name: &str, troll: &TrollConfig) -> Option<ScratchProgram> {
+        let complex = match self {
+            RunKind::Trivial(_) => return None,
+            RunKind::ComplexRun(complex) => complex,
+        };
+        let origin = match (&complex.source, &complex.path) {
+            (Option::Some(source), _) => ProgramSource::Inline(source.clone()),
+            (Option::None, Option::Some(path)) if !complex.substitute.is_empty() => {
+                ProgramSource::File(resolve(&self.cwd(troll), path))
+            }
+            _ => return None,
+        };
+        Some(ScratchProgram {
+            path: scratch_path(name),
+            origin,
+            substitute: complex.substitute.clone(),
+        })
+    }
+
+    /// the name of the executable in `[troll.bins]` the run picked
+    fn bin(&self) -> Option<String> {
+        match self {
+            RunKind::Trivial(_) => None,
+            RunKind::ComplexRun(complex) => complex.bin.clone(),
+        }
+    }
+
+    /// environment variables set for troll, the run's own take precedence over the global ones
+    fn env(&self, troll: &TrollConfig) -> BTreeMap<String, String> {
+        let mut env = troll.env.clone();
+        if let RunKind::ComplexRun(complex) = self {
+            env.extend(complex.env.clone());
+        }
+        env
+    }
+
+    /// should troll start with an empty environment, falling back to the global default
+    fn env_clear(&self, troll: &TrollConfig) -> bool {
+        let env_clear = match self {
+            RunKind::ComplexRun(complex) if complex.env_clear.is_some() => complex.env_clear,
+            _ => troll.env_clear,
+        };
+        env_clear.unwrap_or(false)
+    }
+
+    /// the working directory of troll, falling back to the global default
+    fn cwd(&self, troll: &TrollConfig) -> Option<String> {
+        match self {
+            RunKind::ComplexRun(complex) if complex.cwd.is_some() => complex.cwd.clone(),
+            _ => troll.cwd.clone(),
+        }
+    }
+
+    /// should the mmap workaround be applied up front, falling back to the global default
+    fn mmap_override(&self, troll: &TrollConfig) -> bool {
+        let mmap_override = match self {
+            RunKind::ComplexRun(complex) if complex.mmap_override.is_some() => {
+                complex.mmap_override
+            }
+            _ => troll.mmap_override,
+        };
+        mmap_override.unwrap_or(false)
+    }
+
+    /// the memory limit (in MiB) of the run, falling back to the global limit
+    fn max_memory(&self, troll: &TrollConfig) -> Option<u64> {
+        match self {
+            RunKind::ComplexRun(complex) if complex.max_memory.is_some() => complex.max_memory,
+            _ => troll.max_memory,
+        }
+    }
+
+    /// the number of random rolls to make, `None` means calculate the exact distribution
+    fn samples(&self) -> Option<usize> {
+        match self {
+            RunKind::Trivial(_) => None,
+            RunKind::ComplexRun(complex) => complex.samples(),
         }
     }
 
-    fn add_args(&self, cmd: &mut Command) {
-        cmd.arg("0");
+    /// describes the program & arguments this run executes
+    fn program(&self) -> ProgramInfo {
+        match self {
+            RunKind::Trivial(path) => ProgramInfo {
+                path: path.clone(),
+                ..ProgramInfo::default()
+            },
+            RunKind::ComplexRun(complex) => ProgramInfo {
+                path: complex.path.clone().unwrap_or_else(|| INLINE_PATH.into()),
+                args: complex.args.clone(),
+                source: complex.source.clone(),
+                substitute: complex.substitute.clone(),
+                ..ProgramInfo::default()
+            },
+        }
+    }
+
+    /// returns the label used in output headers (if one was given)
+    fn label(&self) -> Option<String> {
+        match self {
+            RunKind::Trivial(_) => None,
+            RunKind::ComplexRun(complex) => complex.label.clone(),
+        }
+    }
+
+    /// `scratch` is where the program was written to, it replaces the `path`
+    fn add_args(&self, cmd: &mut Command, scratch: Option<&PathBuf>) {
+        // troll's first argument is the number of rolls, zero asks for the distribution
+        match self.samples() {
+            Option::None => cmd.arg("0"),
+            Option::Some(samples) => cmd.arg(samples.to_string()),
+        };
         match self {
             &RunKind::Trivial(ref path) => {
                 cmd.arg(path);
             }
             &RunKind::ComplexRun(ref complex) => {
-                cmd.arg(&complex.path);
+                if let Option::Some(scratch) = scratch {
+                    cmd.arg(scratch);
+                } else if let Option::Some(ref path) = complex.path {
+                    cmd.arg(path);
+                }
                 complex.add_optional_args(cmd);
-                complex.add_mmap(cmd);
             }
         };
     }
 }
 
 /// ComplexRun describes the semantics of runs with variables
-#[derive(Clone, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Deserialize, Debug, PartialEq, Eq, Hash, Default)]
 pub struct ComplexRun {
-    pub path: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    /// the troll program itself, instead of a `path` to it
+    #[serde(default)]
+    pub source: Option<String>,
+    /// replaces `{{NAME}}` placeholders in the program before it is ran
+    #[serde(default)]
+    pub substitute: BTreeMap<String, String>,
+    #[serde(default)]
+    pub label: Option<String>,
     #[serde(default)]
     pub mmap_override: Option<bool>,
     #[serde(default)]
     pub args: BTreeMap<String, usize>,
+    #[serde(default)]
+    pub mode: RunMode,
+    #[serde(default)]
+    pub samples: Option<usize>,
+    #[serde(default)]
+    pub max_memory: Option<u64>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub env_clear: Option<bool>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// which of the `[troll.bins]` executes this run
+    #[serde(default)]
+    pub bin: Option<String>,
 }
 impl ComplexRun {
+    /// a run needs to know what program to execute, and what to execute it with
+    pub fn validate(&self, name: &str, troll: &TrollConfig) -> Result<(), String> {
+        if let Option::Some(ref bin) = self.bin {
+            if !troll.bins.contains_key(bin) {
+                return Err(format!(
+                    "run:'{}' uses bin:'{}' which is not in [troll.bins]",
+                    name, bin
+                ));
+            }
+        }
+        match (self.path.is_some(), self.source.is_some()) {
+            (true, false) | (false, true) => Ok(()),
+            (true, true) => Err(format!(
+                "run:'{}' has both a `path` and a `source`, only one is allowed",
+                name
+            )),
+            (false, false) => Err(format!(
+                "run:'{}' needs either a `path` or a `source`",
+                name
+            )),
+        }
+    }
+
+    /// how many rolls to sample, only meaningful in `RunMode::Sample`
+    fn samples(&self) -> Option<usize> {
+        match self.mode {
+            RunMode::Exact => None,
+            RunMode::Sample => Some(self.samples.unwrap_or(DEFAULT_SAMPLES)),
+        }
+    }
+
     /// manage specifying the arguments
     fn add_optional_args(&self, cmd: &mut Command) {
         for (k, v) in self.args.iter() {
             cmd.arg(&format!("{}={}", k, v));
         }
     }
+}
 
-    /// add_mmap majorly fucks with trolls execution environment.
-    ///
-    /// The reason for this is weird. But you can get the gist if
-    /// you read the Moscow-ML compiler's, TODO list https://github.com/kfl/mosml/blob/master/src/notes/TODO#L1906
-    ///
-    /// > Cody?! Moscow-ML?!? This program executes troll! Not Moscow-ML.
-    ///
-    /// Yes, yes you are correct. Alas, Troll is not a necessarily
-    /// a stand-alone executable. Troll is written in Standard-ML,
-    /// this means that Troll gets compiled to a bytecode (by
-    /// Moscow-ML). Now the Moscowhhhhhhhh-ML's virtual-machine will execute
-    /// Troll. This looks like a normal binary because of some
-    /// clever dynamic linking.
-    ///
-    /// > But, why do we need to mess with Moscow-ML's runtime?
-    ///
-    /// Well we need to ensure `malloc()` calls are served by the `mmap()`
-    /// system call instead of `sbrk()`. This is because (as far as I can tell)
-    /// when `malloc()` starts returning pointers which are "too-far-away"
-    /// from other `malloc()` calls, and it'll OOM itself.
-    ///
-    /// So the plan is to force it to always use MMAP.
-    fn add_mmap(&self, cmd: &mut Command) {
-        match &self.mmap_override {
-            &Option::None | &Option::Some(false) => return,
-            &Option::Some(true) => {}
-        };
+/// add_mmap majorly fucks with trolls execution environment.
+///
+/// The reason for this is weird. But you can get the gist if
+/// you read the Moscow-ML compiler's, TODO list https://github.com/kfl/mosml/blob/master/src/notes/TODO#L1906
+///
+/// > Cody?! Moscow-ML?!? This program executes troll! Not Moscow-ML.
+///
+/// Yes, yes you are correct. Alas, Troll is not a necessarily
+/// a stand-alone executable. Troll is written in Standard-ML,
+/// this means that Troll gets compiled to a bytecode (by
+/// Moscow-ML). Now the Moscowhhhhhhhh-ML's virtual-machine will execute
+/// Troll. This looks like a normal binary because of some
+/// clever dynamic linking.
+///
+/// > But, why do we need to mess with Moscow-ML's runtime?
+///
+/// Well we need to ensure `malloc()` calls are served by the `mmap()`
+/// system call instead of `sbrk()`. This is because (as far as I can tell)
+/// when `malloc()` starts returning pointers which are "too-far-away"
+/// from other `malloc()` calls, and it'll OOM itself.
+///
+/// So the plan is to force it to always use MMAP.
+///
+/// Runs which run out of memory without this are retried once with it applied.
+pub fn add_mmap(cmd: &mut Command) {
+    // remove all the stuff that'll interfere
+    for term in PURGE_GNU_MALLOC_OPTIONS {
+        cmd.env_remove(term);
+    }
 
-        // remove all the stuff that'll interfere
-        for term in PURGE_GNU_MALLOC_OPTIONS {
-            cmd.env_remove(term);
-        }
+    // add stuff that can work together
+    cmd.env("M_MMAP_THRESHOLD", "0");
+    cmd.env("MALLOC_MMAP_THRESHOLD_", "0");
+    cmd.env("M_MMAP_MAX", "4294967295");
+    cmd.env("MALLOC_MMAP_MAX_", "4294967295");
+}
+
+/// limit_memory caps the address space of the troll process.
+///
+/// This happens in the child between `fork()` & `exec()`, so only troll is
+/// effected. When Moscow-ML can't get more memory it gives up, instead of the
+/// whole machine grinding to a halt & the OOM killer picking something random.
+#[cfg(unix)]
+fn limit_memory(cmd: &mut Command, mib: u64) {
+    use super::super::libc;
+    use std::io::Error;
+    use std::os::unix::process::CommandExt;
 
-        // add stuff that can work together
-        cmd.env("M_MMAP_THRESHOLD", "0");
-        cmd.env("MALLOC_MMAP_THRESHOLD_", "0");
-        cmd.env("M_MMAP_MAX", "4294967295");
-        cmd.env("MALLOC_MMAP_MAX_", "4294967295");
+    let bytes = mib.saturating_mul(1024 * 1024) as libc::rlim_t;
+    let limit = libc::rlimit {
+        rlim_cur: bytes,
+        rlim_max: bytes,
+    };
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        });
     }
 }
 
+#[cfg(not(unix))]
+fn limit_memory(_: &mut Command, _: u64) {}
+
+/// executable is the troll a run executes, runs without a `bin` use the `[troll] path`
+fn executable<'a>(troll: &'a TrollConfig, bin: &Option<String>) -> &'a str {
+    let bin = bin.as_ref().and_then(|bin| troll.bins.get(bin));
+    match (bin, &troll.path) {
+        (Option::Some(bin), _) => bin,
+        (Option::None, Option::Some(path)) => path,
+        (Option::None, Option::None) => "troll",
+    }
+}
+
+/// resolve finds a program the way troll will, relative to its working directory
+pub fn resolve(cwd: &Option<String>, path: &str) -> String {
+    match cwd {
+        Option::Some(cwd) => Path::new(cwd).join(path).to_string_lossy().into_owned(),
+        Option::None => path.to_string(),
+    }
+}
+
+/// how many scratch files this process has named, so no two runs share one
+static SCRATCH_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// scratch_path is where the program of a run is written before troll is invoked.
+///
+/// Names which differ only in punctuation sanitize to the same string, the
+/// counter keeps their files apart.
+fn scratch_path(name: &str) -> PathBuf {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let count = SCRATCH_FILES.fetch_add(1, Ordering::SeqCst);
+    temp_dir().join(format!(
+        "trollrun-{}-{}-{}.t",
+        ::std::process::id(),
+        count,
+        name
+    ))
+}
+
+/// RunMode states how troll should be invoked
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RunMode {
+    /// calculate the exact distribution
+    #[default]
+    Exact,
+    /// roll the dice many times, and build an empirical distribution
+    Sample,
+}
+
+/// recorded as the path of runs with an inline `source`
+const INLINE_PATH: &str = "<inline>";
+
+/// how many rolls are made if `mode = "sample"` is given without `samples`
+const DEFAULT_SAMPLES: usize = 10_000;
+
 const PURGE_GNU_MALLOC_OPTIONS: &'static [&'static str] = &[
     "M_MMAP_MAX",
     "MALLOC_MMAP_MAX_",
@@ -132,3 +449,265 @@ reroll_one_dda = { path = "lol_dda.t", args = { rr1 = 1 } }
         &RunKind::Trivial("lol_dda.t".to_string())
     );
 }
+
+#[test]
+fn test_labeled_config() {
+    use super::super::toml::from_str;
+
+    let dut = r#"
+hb_bs4 = { path = "heavy_bolter.t", label = "Heavy Bolter (BS4, rerolls)", args = { bs = 4 } }
+"#;
+
+    let o = from_str::<BTreeMap<String, RunKind>>(dut).unwrap();
+    let run =
+        o.get("hb_bs4")
+            .unwrap()
+            .into_exec(&"hb_bs4".to_string(), &TrollConfig::default(), false);
+    assert_eq!(run.name, "hb_bs4");
+    assert_eq!(run.label, Some("Heavy Bolter (BS4, rerolls)".to_string()));
+}
+
+#[test]
+fn test_sample_config() {
+    use super::super::toml::from_str;
+
+    let dut = r#"
+exact = { path = "big.t" }
+sampled = { path = "big.t", mode = "sample", samples = 5000 }
+default_sampled = { path = "big.t", mode = "sample" }
+"#;
+
+    let o = from_str::<BTreeMap<String, RunKind>>(dut).unwrap();
+    let samples = |name: &str| {
+        o.get(name)
+            .unwrap()
+            .into_exec(&name.to_string(), &TrollConfig::default(), false)
+            .samples
+    };
+    assert_eq!(samples("exact"), None);
+    assert_eq!(samples("sampled"), Some(5000));
+    assert_eq!(samples("default_sampled"), Some(DEFAULT_SAMPLES));
+}
+
+#[test]
+fn test_max_memory_config() {
+    use super::super::toml::from_str;
+
+    let dut = r#"
+global = "small.t"
+own = { path = "big.t", max_memory = 8192 }
+"#;
+
+    let o = from_str::<BTreeMap<String, RunKind>>(dut).unwrap();
+    let troll = TrollConfig {
+        max_memory: Some(2048),
+        ..TrollConfig::default()
+    };
+    let max_memory = |name: &str| {
+        o.get(name)
+            .unwrap()
+            .into_exec(&name.to_string(), &troll, false)
+            .max_memory
+    };
+    assert_eq!(max_memory("global"), Some(2048));
+    assert_eq!(max_memory("own"), Some(8192));
+}
+
+#[test]
+fn test_mmap_override_config() {
+    use super::super::toml::from_str;
+
+    let dut = r#"
+global = "small.t"
+own = { path = "big.t", mmap_override = false }
+"#;
+
+    let o = from_str::<BTreeMap<String, RunKind>>(dut).unwrap();
+    let mmap = |name: &str, troll: &TrollConfig| {
+        o.get(name)
+            .unwrap()
+            .into_exec(&name.to_string(), troll, false)
+            .mmap
+    };
+    let troll = TrollConfig {
+        mmap_override: Some(true),
+        ..TrollConfig::default()
+    };
+    assert!(!mmap("global", &TrollConfig::default()));
+    assert!(mmap("global", &troll));
+    assert!(!mmap("own", &troll));
+}
+
+#[test]
+fn test_inline_source_config() {
+    use super::super::toml::from_str;
+
+    let dut = r#"
+[inline]
+source = """
+sum 3d6
+"""
+
+[neither]
+label = "nothing to run"
+
+[both]
+path = "3d6.t"
+source = "sum 3d6"
+"#;
+
+    let o = from_str::<BTreeMap<String, RunKind>>(dut).unwrap();
+    let complex = |name: &str| match o.get(name).unwrap() {
+        RunKind::ComplexRun(complex) => (**complex).clone(),
+        RunKind::Trivial(_) => panic!("expected a complex run"),
+    };
+    assert!(complex("inline")
+        .validate("inline", &TrollConfig::default())
+        .is_ok());
+    assert!(complex("neither")
+        .validate("neither", &TrollConfig::default())
+        .is_err());
+    assert!(complex("both")
+        .validate("both", &TrollConfig::default())
+        .is_err());
+
+    let run =
+        o.get("inline")
+            .unwrap()
+            .into_exec(&"inline".to_string(), &TrollConfig::default(), false);
+    assert_eq!(run.program.path, INLINE_PATH);
+    assert_eq!(run.program.source, Some("sum 3d6\n".to_string()));
+    assert!(run.scratch.is_some());
+}
+
+#[test]
+fn test_substitute_config() {
+    use super::super::toml::from_str;
+
+    let dut = r#"
+plain = { path = "attack.t" }
+d8 = { path = "attack.t", substitute = { DIE = "d8" } }
+"#;
+
+    let o = from_str::<BTreeMap<String, RunKind>>(dut).unwrap();
+    let run = |name: &str| {
+        o.get(name)
+            .unwrap()
+            .into_exec(&name.to_string(), &TrollConfig::default(), false)
+    };
+    assert!(run("plain").scratch.is_none());
+    let d8 = run("d8");
+    assert_eq!(d8.program.path, "attack.t");
+    assert_eq!(d8.program.substitute.get("DIE"), Some(&"d8".to_string()));
+    match d8.scratch.as_ref().unwrap().origin {
+        ProgramSource::File(ref path) => assert_eq!(path, "attack.t"),
+        ProgramSource::Inline(_) => panic!("expected the program to come from a file"),
+    }
+}
+
+#[test]
+fn test_environment_config() {
+    use super::super::toml::from_str;
+    use std::ffi::OsStr;
+
+    let dut = r#"
+global = "small.t"
+own = { path = "big.t", cwd = "/srv/troll", env = { CAMLRUNPARAM = "h=1G", TROLL_SEED = "7" } }
+"#;
+
+    let o = from_str::<BTreeMap<String, RunKind>>(dut).unwrap();
+    let mut troll = TrollConfig::default();
+    troll.env.insert("CAMLRUNPARAM".into(), "h=256M".into());
+    troll.cwd = Some("/tmp".into());
+    let run = |name: &str| {
+        o.get(name)
+            .unwrap()
+            .into_exec(&name.to_string(), &troll, false)
+    };
+
+    let global = run("global");
+    assert_eq!(global.cmd.get_current_dir(), Some(Path::new("/tmp")));
+    assert_eq!(global.program.env.get("CAMLRUNPARAM").unwrap(), "h=256M");
+
+    let own = run("own");
+    assert_eq!(own.program.cwd, Some("/srv/troll".to_string()));
+    assert_eq!(own.program.env.get("CAMLRUNPARAM").unwrap(), "h=1G");
+    assert!(own
+        .cmd
+        .get_envs()
+        .any(|(k, v)| k == OsStr::new("TROLL_SEED") && v == Some(OsStr::new("7"))));
+}
+
+#[test]
+fn test_bins_config() {
+    use super::super::toml::from_str;
+    use std::ffi::OsStr;
+
+    let dut = r#"
+default = "attack.t"
+stock = { path = "attack.t", bin = "stock" }
+patched = { path = "attack.t", bin = "patched" }
+missing = { path = "attack.t", bin = "nightly" }
+"#;
+
+    let o = from_str::<BTreeMap<String, RunKind>>(dut).unwrap();
+    let mut troll = TrollConfig::default();
+    troll.bins.insert("stock".into(), "/opt/troll/stock".into());
+    troll
+        .bins
+        .insert("patched".into(), "/opt/troll/patched".into());
+    let run = |name: &str| {
+        o.get(name)
+            .unwrap()
+            .into_exec(&name.to_string(), &troll, false)
+    };
+
+    assert_eq!(run("default").cmd.get_program(), OsStr::new("troll"));
+    assert_eq!(run("default").program.bin, None);
+    assert_eq!(
+        run("stock").cmd.get_program(),
+        OsStr::new("/opt/troll/stock")
+    );
+    assert_eq!(run("patched").program.bin, Some("patched".to_string()));
+    match o.get("missing").unwrap() {
+        RunKind::ComplexRun(complex) => assert!(complex.validate("missing", &troll).is_err()),
+        RunKind::Trivial(_) => panic!("expected a complex run"),
+    }
+}
+
+#[test]
+fn test_scratch_path() {
+    assert_ne!(scratch_path("a/b"), scratch_path("a_b"));
+    assert_ne!(scratch_path("same"), scratch_path("same"));
+}
+
+#[test]
+fn test_fingerprint() {
+    use std::fs::{remove_file, write};
+
+    let path = temp_dir().join(format!("trollrun-{}-fingerprint.t", ::std::process::id()));
+    let file = path.to_string_lossy().into_owned();
+    let troll = TrollConfig::default();
+    let trivial = RunKind::Trivial(file.clone());
+    let complex = |rr1: usize| {
+        let mut args = BTreeMap::new();
+        args.insert("rr1".to_string(), rr1);
+        RunKind::ComplexRun(Box::new(ComplexRun {
+            path: Some(file.clone()),
+            args,
+            ..Default::default()
+        }))
+    };
+
+    write(&path, "sum 3d6").unwrap();
+    let before = trivial.fingerprint(&troll);
+    assert_eq!(before, trivial.fingerprint(&troll));
+    assert_ne!(
+        complex(1).fingerprint(&troll),
+        complex(2).fingerprint(&troll)
+    );
+    write(&path, "sum 4d6").unwrap();
+    assert_ne!(before, trivial.fingerprint(&troll));
+    assert_eq!(trivial.watched_files(&troll), vec![path.clone()]);
+    remove_file(&path).unwrap();
+}
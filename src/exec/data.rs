@@ -1,14 +1,167 @@
+use std::collections::BTreeMap;
 use std::iter::{FromIterator, IntoIterator, Iterator};
 use std::ops::{Index, IndexMut};
 
-use super::parser::TrollLine;
+use super::super::serde::{Deserialize, Serialize};
+
+use super::parser::{Outcome, TrollLine, TrollSummary};
 
 /// TrollData is an opaque type that contains information prior
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TrollData {
+    #[serde(rename = "points")]
     data: DataCollector,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    samples: Option<usize>,
+    /// the statistics troll printed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summary: Option<TrollSummary>,
+    /// lines of troll's output which looked like data, but couldn't be parsed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unparsed: Vec<String>,
+    /// what the sanity checks found wrong with the lines troll printed,
+    /// these are kept so runs read back from the journal are checked too
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    problems: Vec<String>,
 }
 impl TrollData {
+    /// builds an empirical distribution from the outcomes of random rolls
+    pub fn from_samples(values: &[i64]) -> TrollData {
+        let mut counts: BTreeMap<i64, usize> = BTreeMap::new();
+        for value in values {
+            *counts.entry(*value).or_insert(0) += 1;
+        }
+        let total = values.len() as f64;
+        let mut lines: Vec<TrollLine> = counts
+            .into_iter()
+            .map(|(value, count)| TrollLine {
+                outcome: Outcome::Integer(value),
+                prob: 100.0 * count as f64 / total,
+                accum: 0.0,
+            })
+            .collect();
+        // fill in the accumulated probabilities, like troll would
+        let mut accum = 0.0;
+        for line in lines.iter_mut().rev() {
+            accum += line.prob;
+            line.accum = accum;
+        }
+        let mut data: TrollData = lines.into_iter().collect();
+        data.samples = Some(values.len());
+        data
+    }
+
+    /// how many rolls the data was built from, `None` if it is an exact distribution
+    pub fn samples(&self) -> Option<usize> {
+        self.samples
+    }
+
+    /// the statistics troll printed after the distribution, if it did
+    pub fn summary(&self) -> Option<TrollSummary> {
+        self.summary
+    }
+
+    /// attaches the statistics troll printed
+    pub fn set_summary(&mut self, summary: TrollSummary) {
+        self.summary = Some(summary);
+    }
+
+    /// the lines of troll's output which looked like data, but couldn't be parsed
+    pub fn unparsed(&self) -> &[String] {
+        &self.unparsed
+    }
+
+    /// records the lines which couldn't be parsed
+    pub fn set_unparsed(&mut self, unparsed: Vec<String>) {
+        self.unparsed = unparsed;
+    }
+
+    /// everything which looks wrong with the data: lines which couldn't be parsed,
+    /// a distribution which doesn't add up, & statistics which don't match troll's
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self
+            .unparsed
+            .iter()
+            .map(|line| format!("a line of troll's output could not be parsed: {}", line))
+            .collect();
+        warnings.extend(self.problems.iter().cloned());
+        if let Err(e) = self.check_summary() {
+            warnings.push(format!(
+                "troll's summary doesn't match the parsed distribution: {}",
+                e
+            ));
+        }
+        warnings
+    }
+
+    /// troll's statistics, or the computed ones when troll didn't print them.
+    ///
+    /// There are none for outcomes which aren't numbers.
+    pub fn statistics(&self) -> Option<TrollSummary> {
+        if self.summary.is_some() || !self.is_numeric() {
+            return self.summary;
+        }
+        Some(TrollSummary {
+            average: self.mean(),
+            spread: self.spread(),
+            mean_deviation: self.mean_deviation(),
+        })
+    }
+
+    /// compares troll's statistics with the ones computed from the parsed distribution.
+    ///
+    /// Troll prints 12 significant digits, so anything more than a rounding error
+    /// apart means the distribution was not parsed correctly.
+    pub fn check_summary(&self) -> Result<(), String> {
+        let summary = match self.summary {
+            Option::Some(summary) if self.is_numeric() => summary,
+            _ => return Ok(()),
+        };
+        let checks = [
+            ("Average", summary.average, self.mean()),
+            ("Spread", summary.spread, self.spread()),
+            (
+                "Mean deviation",
+                summary.mean_deviation,
+                self.mean_deviation(),
+            ),
+        ];
+        let mismatches: Vec<String> = checks
+            .iter()
+            .filter(|(_, printed, computed)| {
+                (printed - computed).abs() > SUMMARY_TOLERANCE * printed.abs().max(1.0)
+            })
+            .map(|(name, printed, computed)| {
+                format!("{} = {} but {} was computed", name, printed, computed)
+            })
+            .collect();
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches.join(", "))
+        }
+    }
+
+    /// the 95% confidence interval of a percentage taken from this data.
+    ///
+    /// This uses the Wilson score interval, which (unlike the normal approximation)
+    /// behaves for values which were never or always rolled. Exact distributions
+    /// have no interval.
+    pub fn interval(&self, percent: f64) -> Option<(f64, f64)> {
+        let n = match self.samples {
+            Option::Some(n) if n > 0 => n as f64,
+            _ => return None,
+        };
+        let z = 1.96f64;
+        let p = percent / 100.0;
+        let denominator = 1.0 + z * z / n;
+        let center = (p + z * z / (2.0 * n)) / denominator;
+        let half = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+        let low = (center - half).max(0.0);
+        let high = (center + half).min(1.0);
+        Some((100.0 * low, 100.0 * high))
+    }
+
     /// how long is this chunk of data?
     pub fn len(&self) -> usize {
         self.data.len()
@@ -19,20 +172,55 @@ impl TrollData {
         self.data.is_empty()
     }
 
-    /// returns the maximum base value in the array (this should be equal to the last index)
-    pub fn last_value(&self) -> usize {
-        if self.data.is_empty() {
+    /// are all the outcomes numbers? Only then are the points every value from the first to the last.
+    pub fn is_numeric(&self) -> bool {
+        self.data.is_numeric()
+    }
+
+    /// the outcomes, in the order they're written
+    pub fn outcomes(&self) -> Vec<Outcome> {
+        self.data
+            .data
+            .iter()
+            .map(|point| point.value.clone())
+            .collect()
+    }
+
+    /// returns the minimum base value in the array (this is 0, unless there are negative values)
+    pub fn first_value(&self) -> i64 {
+        if self.data.is_empty() || !self.is_numeric() {
+            0
+        } else {
+            self.data.first_value()
+        }
+    }
+
+    /// returns the maximum base value in the array
+    pub fn last_value(&self) -> i64 {
+        if self.data.is_empty() || !self.is_numeric() {
             0
         } else {
             self.data.last_value()
         }
     }
 
+    /// pad_from is `pad_to` for the other end, inserting zero probability values before
+    /// the first one
+    pub fn pad_from(&mut self, first_value: i64) {
+        let own_first = self.first_value();
+        if self.data.is_empty() || own_first <= first_value || !self.is_numeric() {
+            return;
+        }
+        let padding = (first_value..own_first).map(DataPoint::prob_zero);
+        self.data.data.splice(0..0, padding);
+        self.data.accumulate();
+    }
+
     /// pad_to will insert junk data to ensure our last value is equal to the requested
     /// this junk data is "zero probability events"
-    pub fn pad_to(&mut self, last_value: usize) {
+    pub fn pad_to(&mut self, last_value: i64) {
         let own_last = self.last_value();
-        if own_last >= last_value {
+        if own_last >= last_value || !self.is_numeric() {
             return;
         }
         for curr in (own_last + 1)..(last_value + 1) {
@@ -40,11 +228,88 @@ impl TrollData {
         }
     }
 
+    /// align_to reorders the points to follow `outcomes`, this is `pad_to` for
+    /// outcomes which aren't numbers. Outcomes this data doesn't have get a zero
+    /// probability, & outcomes which aren't listed are dropped.
+    pub fn align_to(&mut self, outcomes: &[Outcome]) {
+        let mut points: BTreeMap<Outcome, DataPoint> = self
+            .data
+            .data
+            .drain(..)
+            .map(|point| (point.value.clone(), point))
+            .collect();
+        for outcome in outcomes {
+            let point = points.remove(outcome).unwrap_or(DataPoint {
+                value: outcome.clone(),
+                prob: 0.0,
+                accum: 0.0,
+            });
+            self.data.append(point);
+        }
+        self.data.accumulate();
+    }
+
     /// this is sort of the inverse of `pad_to` instead of adding values which are zero
     /// it removes them.
     pub fn trim_less_than(&mut self, cutoff: &f64) {
         self.data.remove_smaller(cutoff);
     }
+
+    /// the expected value of the distribution
+    pub fn mean(&self) -> f64 {
+        self.data
+            .data
+            .iter()
+            .filter_map(|point| point.value.integer().map(|v| v as f64 * point.prob / 100.0))
+            .sum()
+    }
+
+    /// the standard deviation of the distribution
+    pub fn spread(&self) -> f64 {
+        let mean = self.mean();
+        let variance: f64 = self
+            .data
+            .data
+            .iter()
+            .filter_map(|point| {
+                let value = point.value.integer()? as f64;
+                Some((value - mean).powi(2) * point.prob / 100.0)
+            })
+            .sum();
+        variance.sqrt()
+    }
+
+    /// the mean absolute deviation from the expected value
+    pub fn mean_deviation(&self) -> f64 {
+        let mean = self.mean();
+        self.data
+            .data
+            .iter()
+            .filter_map(|point| {
+                let value = point.value.integer()? as f64;
+                Some((value - mean).abs() * point.prob / 100.0)
+            })
+            .sum()
+    }
+
+    /// the smallest value which can occur
+    pub fn min_value(&self) -> Option<i64> {
+        self.data
+            .data
+            .iter()
+            .filter(|point| point.prob > 0.0)
+            .find_map(|point| point.value.integer())
+    }
+
+    /// the largest value which can occur
+    pub fn max_value(&self) -> Option<i64> {
+        self.data
+            .data
+            .iter()
+            .rev()
+            .filter(|point| point.prob > 0.0)
+            .find_map(|point| point.value.integer())
+    }
 }
 impl Index<usize> for TrollData {
     type Output = DataPoint;
@@ -60,26 +325,40 @@ impl IndexMut<usize> for TrollData {
 
 impl FromIterator<TrollLine> for TrollData {
     fn from_iter<T: IntoIterator<Item = TrollLine>>(iter: T) -> TrollData {
-        let iter = iter.into_iter();
+        let lines: Vec<TrollLine> = iter.into_iter().collect();
+        let problems = check_lines(&lines);
+        let mut vec = DataCollector::with_capacity(lines.len());
 
-        // see if we can do pre-sizing
-        let mut vec = match iter.size_hint() {
-            (0, Option::None) | (0, Option::Some(0)) => DataCollector::default(),
-            (x, Option::None) | (_, Option::Some(x)) => DataCollector::with_capacity(x),
-        };
+        // outcomes which aren't numbers are kept in the order troll printed them
+        if !lines.iter().all(|line| line.outcome.integer().is_some()) {
+            for item in lines {
+                vec.append(DataPoint::from(item));
+            }
+            vec.accumulate();
+            return TrollData {
+                data: vec,
+                samples: None,
+                summary: None,
+                unparsed: Vec::new(),
+                problems,
+            };
+        }
 
-        for item in iter {
-            // handle the case if our initial value is not zero
-            if vec.is_empty() && item.base_value >= 1 {
-                for value in 0..item.base_value {
+        for item in lines {
+            let base_value = item.outcome.integer().unwrap_or(0);
+
+            // handle the case if our initial value is not zero,
+            // negative values start the distribution themselves
+            if vec.is_empty() && base_value >= 1 {
+                for value in 0..base_value {
                     vec.append(DataPoint::prob_zero(value));
                 }
             }
 
             // handle the case if our last value is != next value
-            if !vec.is_empty() && (vec.last_value() + 1) < item.base_value {
+            if !vec.is_empty() && (vec.last_value() + 1) < base_value {
                 let last = vec.last_value() + 1;
-                let next = item.base_value;
+                let next = base_value;
                 for value in last..next {
                     vec.append(DataPoint::prob_zero(value));
                 }
@@ -90,27 +369,92 @@ impl FromIterator<TrollLine> for TrollData {
         }
 
         // now we loop over the array (in reverse order) and double check accumlation values
-        let mut accum_total = 0.0;
-        for i in (0..vec.len()).rev() {
-            accum_total += vec[i].prob;
-            vec[i].accum = accum_total;
-        }
+        vec.accumulate();
 
         // shove the data out the door
-        TrollData { data: vec }
+        TrollData {
+            data: vec,
+            samples: None,
+            summary: None,
+            unparsed: Vec::new(),
+            problems,
+        }
+    }
+}
+
+/// sanity checks on the lines troll printed, before they're padded & accumulated.
+///
+/// The probabilities have to add up to 100%, numbers have to be strictly increasing
+/// (other outcomes can't repeat), & the accumulated probabilities troll printed have
+/// to match the ones computed from the probabilities.
+fn check_lines(lines: &[TrollLine]) -> Vec<String> {
+    let mut problems = Vec::new();
+    if lines.is_empty() {
+        return problems;
+    }
+    let total: f64 = lines.iter().map(|line| line.prob).sum();
+    if (total - 100.0).abs() > PERCENT_TOLERANCE {
+        problems.push(format!("the probabilities add up to {}%, not 100%", total));
+    }
+
+    if !lines.iter().all(|line| line.outcome.integer().is_some()) {
+        let mut outcomes: Vec<&Outcome> = lines.iter().map(|line| &line.outcome).collect();
+        outcomes.sort();
+        for pair in outcomes.windows(2) {
+            if pair[0] == pair[1] {
+                problems.push(format!("outcome {} appears more than once", pair[0]));
+            }
+        }
+        // troll's accumulated probabilities are only meaningful for numbers
+        return problems;
+    }
+
+    for pair in lines.windows(2) {
+        if pair[0].outcome >= pair[1].outcome {
+            problems.push(format!(
+                "value {} follows {}, values should be strictly increasing",
+                pair[1].outcome, pair[0].outcome
+            ));
+        }
+    }
+    // a missing line throws off the accumulated probability of every value below it,
+    // the highest value which disagrees is closest to the cause
+    let mut accum = 0.0;
+    let mut mismatches = Vec::new();
+    for line in lines.iter().rev() {
+        accum += line.prob;
+        if (line.accum - accum).abs() > PERCENT_TOLERANCE {
+            mismatches.push((line, accum));
+        }
     }
+    if let Option::Some((line, accum)) = mismatches.first() {
+        problems.push(format!(
+            "the accumulated probability of value {} is {}% but {}% was computed ({} values disagree)",
+            line.outcome,
+            line.accum,
+            accum,
+            mismatches.len()
+        ));
+    }
+    problems
 }
 
-#[derive(Clone, Default)]
+/// how far (in percentage points) probabilities may be apart, troll prints 12 significant digits
+const PERCENT_TOLERANCE: f64 = 1e-6;
+
+/// how far (relative to the value) troll's statistics may be from the computed ones
+const SUMMARY_TOLERANCE: f64 = 1e-6;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DataPoint {
-    pub value: usize,
+    pub value: Outcome,
     pub prob: f64,
     pub accum: f64,
 }
 impl From<TrollLine> for DataPoint {
     fn from(arg: TrollLine) -> DataPoint {
         DataPoint {
-            value: arg.base_value,
+            value: arg.outcome,
             prob: arg.prob,
             accum: arg.accum,
         }
@@ -118,9 +462,9 @@ impl From<TrollLine> for DataPoint {
 }
 impl DataPoint {
     /// generates a place holder value
-    fn prob_zero(value: usize) -> DataPoint {
+    fn prob_zero(value: i64) -> DataPoint {
         DataPoint {
-            value,
+            value: Outcome::Integer(value),
             prob: 0.0,
             accum: 0.0,
         }
@@ -132,7 +476,8 @@ impl DataPoint {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 struct DataCollector {
     data: Vec<DataPoint>,
 }
@@ -162,11 +507,39 @@ impl DataCollector {
         self.data.len()
     }
 
-    fn last_value(&self) -> usize {
+    fn first_value(&self) -> i64 {
+        if self.is_empty() {
+            panic!("cannot take first value when no values exist");
+        }
+        match self.data[0].value.integer() {
+            Option::Some(value) => value,
+            Option::None => panic!("cannot take first value when values are not numbers"),
+        }
+    }
+
+    fn last_value(&self) -> i64 {
         if self.is_empty() {
             panic!("cannot take last value when no values exist");
         }
-        self.data[self.data.len() - 1].value
+        match self.data[self.data.len() - 1].value.integer() {
+            Option::Some(value) => value,
+            Option::None => panic!("cannot take last value when values are not numbers"),
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        self.data
+            .iter()
+            .all(|point| point.value.integer().is_some())
+    }
+
+    /// recomputes the accumulated probabilities, from the last point backwards
+    fn accumulate(&mut self) {
+        let mut accum_total = 0.0;
+        for i in (0..self.len()).rev() {
+            accum_total += self.data[i].prob;
+            self.data[i].accum = accum_total;
+        }
     }
 
     fn append(&mut self, arg: DataPoint) {
@@ -247,6 +620,92 @@ fn test_full_output() {
     }
 }
 
+#[test]
+fn test_check_summary() {
+    for dut in &[
+        include_str!("dda_test_run_output"),
+        include_str!("pylon.data"),
+    ] {
+        let mut output: TrollData = dut.lines().filter_map(TrollLine::new).collect();
+        let summary = dut.lines().filter_map(TrollSummary::new).next().unwrap();
+        output.set_summary(summary);
+        assert_eq!(output.check_summary(), Ok(()));
+        assert_eq!(output.statistics(), Some(summary));
+    }
+
+    // a row which was not parsed shifts the average
+    let dut = include_str!("dda_test_run_output");
+    let mut output: TrollData = dut
+        .lines()
+        .filter_map(TrollLine::new)
+        .filter(|line| line.outcome != Outcome::Integer(6))
+        .collect();
+    output.set_summary(dut.lines().filter_map(TrollSummary::new).next().unwrap());
+    assert!(output
+        .check_summary()
+        .unwrap_err()
+        .starts_with("Average = 2.72222222222"));
+}
+
+#[test]
+fn test_sanity_checks() {
+    for dut in &[
+        include_str!("dda_test_run_output"),
+        include_str!("pylon.data"),
+    ] {
+        let output: TrollData = dut.lines().filter_map(TrollLine::new).collect();
+        assert!(output.warnings().is_empty(), "{:?}", output.warnings());
+    }
+    assert!(TrollData::from_samples(&[1, 3, 3, 7]).warnings().is_empty());
+
+    // a line which was not parsed
+    let dut = include_str!("dda_test_run_output");
+    let output: TrollData = dut
+        .lines()
+        .filter_map(TrollLine::new)
+        .filter(|line| line.outcome != Outcome::Integer(6))
+        .collect();
+    let warnings = output.warnings();
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert!(warnings[0].starts_with("the probabilities add up to 91.8"));
+    assert!(warnings[1].starts_with("the accumulated probability of value 5 is 27.98"));
+    assert!(warnings[1].ends_with("(6 values disagree)"));
+
+    let dut = "  0: 25.0 100.0\n  2: 25.0 25.0\n  1: 50.0 75.0\n";
+    let output: TrollData = dut.lines().filter_map(TrollLine::new).collect();
+    assert_eq!(
+        output.warnings()[0],
+        "value 1 follows 2, values should be strictly increasing"
+    );
+
+    let dut = "  \"hit\": 50.0\n  \"miss\": 25.0\n  \"hit\": 25.0\n";
+    let output: TrollData = dut.lines().filter_map(TrollLine::new).collect();
+    assert_eq!(
+        output.warnings(),
+        vec!["outcome hit appears more than once"]
+    );
+}
+
+#[test]
+fn test_outcomes() {
+    let dut = "  1 3 :    50.0\n  \"miss\":    25.0\n  {}:    25.0\n";
+    let mut output: TrollData = dut.lines().filter_map(TrollLine::new).collect();
+
+    assert!(!output.is_numeric());
+    assert_eq!(output.len(), 3);
+    assert_eq!(output[0].value, Outcome::Multiset(vec![1, 3]));
+    assert_eq!(output[0].accum, 100.0);
+    assert_eq!(output[2].accum, 25.0);
+    assert_eq!(output.statistics(), None);
+
+    let hit = Outcome::Text("hit".to_string());
+    let miss = Outcome::Text("miss".to_string());
+    output.align_to(&[hit.clone(), miss.clone()]);
+    assert_eq!(output.outcomes(), vec![hit, miss]);
+    assert_eq!(output[0].prob, 0.0);
+    assert_eq!(output[1].prob, 25.0);
+}
+
 #[test]
 fn test_weird_pylon_table() {
     let dut = include_str!("pylon.data");
@@ -258,3 +717,57 @@ fn test_weird_pylon_table() {
     assert_eq!(output[16].prob, 9.6660149794);
     assert_eq!(output[17].prob, 0.0);
 }
+
+#[test]
+fn test_summary_statistics() {
+    let dut = "  0: 25.0 100.0\n  1: 50.0 75.0\n  2: 25.0 25.0\n";
+    let output: TrollData = dut.lines().filter_map(TrollLine::new).collect();
+
+    assert_eq!(output.mean(), 1.0);
+    assert_eq!(output.spread(), 0.5f64.sqrt());
+    assert_eq!(output.mean_deviation(), 0.5);
+    assert_eq!(output.min_value(), Some(0));
+    assert_eq!(output.max_value(), Some(2));
+}
+
+#[test]
+fn test_from_samples() {
+    let output = TrollData::from_samples(&[1, 3, 3, 1, 3, 3, 1, 3]);
+
+    assert_eq!(output.samples(), Some(8));
+    assert_eq!(output.len(), 4);
+    assert_eq!(output[0].prob, 0.0);
+    assert_eq!(output[1].prob, 37.5);
+    assert_eq!(output[2].prob, 0.0);
+    assert_eq!(output[3].prob, 62.5);
+    assert_eq!(output[0].accum, 100.0);
+    assert_eq!(output[3].accum, 62.5);
+
+    // values which were never rolled still get a non-zero upper bound
+    let (low, high) = output.interval(output[2].prob).unwrap();
+    assert_eq!(low, 0.0);
+    assert!(high > 0.0 && high < 50.0);
+    let (low, high) = output.interval(output[1].prob).unwrap();
+    assert!(low < 37.5 && 37.5 < high);
+}
+
+#[test]
+fn test_negative_values() {
+    let dut = "  ~2: 25.0 100.0\n  0: 50.0 75.0\n  1: 25.0 25.0\n";
+    let mut output: TrollData = dut.lines().filter_map(TrollLine::new).collect();
+
+    assert!(output.warnings().is_empty());
+    assert_eq!(output.outcomes()[0], Outcome::Integer(-2));
+    assert_eq!(output.len(), 4);
+    assert_eq!(output[1].prob, 0.0);
+    assert_eq!(output[1].accum, 75.0);
+    assert_eq!(output.first_value(), -2);
+    assert_eq!(output.min_value(), Some(-2));
+    assert_eq!(output.mean(), -0.25);
+
+    // other runs can start lower still
+    output.pad_from(-3);
+    assert_eq!(output.outcomes()[0], Outcome::Integer(-3));
+    assert_eq!(output[0].accum, 100.0);
+    assert_eq!(output.min_value(), Some(-2));
+}
@@ -0,0 +1,141 @@
+use std::ffi::OsStr;
+use std::fmt::Write;
+
+use super::runs::{columns, ProgramSource, RunResult, TrollRun};
+
+/// describe explains what executing a run would do, without executing it.
+///
+/// The human readable part is followed by a shell command which does the same thing.
+pub fn describe(run: &TrollRun) -> String {
+    let mut out = String::new();
+    let cmd = &run.cmd;
+    let _ = writeln!(out, "run: {}", run.name);
+    let _ = writeln!(
+        out,
+        "  columns: {}",
+        columns(&run.name, &run.label, run.samples.is_some()).join(", ")
+    );
+    let _ = writeln!(out, "  executable: {}", cmd.get_program().to_string_lossy());
+    let args: Vec<String> = cmd.get_args().map(lossy).collect();
+    let _ = writeln!(out, "  arguments: {}", args.join(" "));
+    match cmd.get_current_dir() {
+        Option::Some(dir) => {
+            let _ = writeln!(out, "  working directory: {}", dir.display());
+        }
+        Option::None => {
+            let _ = writeln!(out, "  working directory: (inherited)");
+        }
+    };
+    if run.env_clear {
+        let _ = writeln!(out, "  environment: cleared");
+    }
+    for (key, value) in cmd.get_envs() {
+        match value {
+            Option::Some(value) => {
+                let _ = writeln!(out, "  set: {}={}", lossy(key), lossy(value));
+            }
+            Option::None => {
+                let _ = writeln!(out, "  unset: {}", lossy(key));
+            }
+        };
+    }
+    if let Option::Some(mib) = run.max_memory {
+        let _ = writeln!(out, "  memory limit: {} MiB", mib);
+    }
+    if let Option::Some(ref scratch) = run.scratch {
+        let origin = match scratch.origin {
+            ProgramSource::Inline(_) => "inline source".to_string(),
+            ProgramSource::File(ref path) => format!("{} (with substitutions)", path),
+        };
+        let _ = writeln!(
+            out,
+            "  program: {} is written to {}",
+            origin,
+            scratch.path.display()
+        );
+    }
+    let _ = writeln!(out, "  shell:");
+    // not indented, so a heredoc's terminator still works when pasted
+    out.push_str(&shell(run));
+    out
+}
+
+/// shell builds a command which can be pasted into `sh`.
+///
+/// It runs in a subshell, so the `cd` & `ulimit` don't leak.
+fn shell(run: &TrollRun) -> String {
+    let cmd = &run.cmd;
+    let mut out = String::new();
+    if let Option::Some(ref scratch) = run.scratch {
+        let path = quote(&scratch.path.to_string_lossy());
+        match scratch.render() {
+            Ok(program) => {
+                let _ = writeln!(out, "cat > {} <<'TROLLRUN_EOF'", path);
+                out.push_str(&program);
+                if !program.ends_with('\n') {
+                    out.push('\n');
+                }
+                let _ = writeln!(out, "TROLLRUN_EOF");
+            }
+            Err(e) => {
+                let _ = writeln!(out, "# {} cannot be written: {}", path, reason(&e));
+            }
+        };
+    }
+    let mut line: Vec<String> = Vec::new();
+    if let Option::Some(dir) = cmd.get_current_dir() {
+        line.push(format!("cd {} &&", quote(&dir.to_string_lossy())));
+    }
+    if let Option::Some(mib) = run.max_memory {
+        line.push(format!("ulimit -v {} &&", mib.saturating_mul(1024)));
+    }
+    line.push("env".to_string());
+    if run.env_clear {
+        line.push("-i".to_string());
+    }
+    for (key, value) in cmd.get_envs() {
+        if value.is_none() {
+            line.push(format!("-u {}", quote(&lossy(key))));
+        }
+    }
+    for (key, value) in cmd.get_envs() {
+        if let Option::Some(value) = value {
+            line.push(quote(&format!("{}={}", lossy(key), lossy(value))));
+        }
+    }
+    line.push(quote(&lossy(cmd.get_program())));
+    line.extend(cmd.get_args().map(|arg| quote(&lossy(arg))));
+    let _ = writeln!(out, "( {} )", line.join(" "));
+    out
+}
+
+/// quote escapes an argument for `sh`, when it needs to be
+pub fn quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', r#"'\''"#))
+}
+
+fn lossy(arg: &OsStr) -> String {
+    arg.to_string_lossy().into_owned()
+}
+
+fn reason(result: &RunResult) -> String {
+    match result {
+        RunResult::ExecErr(e) => e.to_string(),
+        RunResult::Error(stdout, _) | RunResult::OutOfMemory(stdout, _) => stdout.clone(),
+        RunResult::Invalid(warnings) => warnings.join(", "),
+        RunResult::Interrupted | RunResult::Success(_) => String::new(),
+    }
+}
+
+#[test]
+fn test_quote() {
+    assert_eq!(quote("/usr/bin/troll"), "/usr/bin/troll");
+    assert_eq!(quote("rr1=1"), "rr1=1");
+    assert_eq!(quote(""), "''");
+    assert_eq!(quote("Heavy Bolter"), "'Heavy Bolter'");
+    assert_eq!(quote("it's"), r#"'it'\''s'"#);
+}
@@ -1,36 +1,96 @@
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
 use std::thread::{Builder, JoinHandle};
+use std::time::Duration;
 
-use super::super::cli::AppConfig;
-use super::super::marshal::csv::ser::CSVWriter;
-use super::runs::{TrollRecordable, TrollRun};
-
-pub fn run_program(runs: Vec<TrollRun>, writer: &mut CSVWriter, config: &AppConfig) {
-    // run all the various troll programs and collect their output
-    let output_data: Vec<TrollRecordable> = {
-        // spawn all threads, collect all handles.
-        let mut handles = Vec::with_capacity(runs.len());
-        for item in runs.into_iter() {
-            handles.push(build_thread(item));
+use super::interrupt;
+use super::progress::Progress;
+use super::runs::{RunReport, TrollOutput, TrollRecordable, TrollRun};
+
+/// how often the progress display is refreshed while waiting on troll
+const TICK: Duration = Duration::from_millis(250);
+
+/// what worker threads report back
+enum Event {
+    Started(usize),
+    Finished(usize, Box<TrollOutput>),
+}
+
+/// Batch is everything collected from running troll
+pub struct Batch {
+    /// the results of every successful run
+    pub data: Vec<TrollRecordable>,
+    /// how every run went, including failures
+    pub reports: Vec<RunReport>,
+    /// was the batch cut short by Ctrl-C
+    pub interrupted: bool,
+}
+
+/// run all the various troll programs and collect their output.
+///
+/// `on_result` is given every successful run as soon as it finishes.
+pub fn run_program(
+    runs: Vec<TrollRun>,
+    show_progress: bool,
+    on_result: &mut dyn FnMut(&TrollRecordable),
+) -> Batch {
+    let names: Vec<String> = runs.iter().map(|run| run.name.clone()).collect();
+    let mut progress = Progress::new(names, show_progress);
+    let mut output_data: Vec<Option<TrollRecordable>> = runs.iter().map(|_| None).collect();
+    let mut reports: Vec<Option<RunReport>> = runs.iter().map(|_| None).collect();
+
+    // spawn all threads, collect all handles.
+    let (tx, rx) = channel();
+    let mut handles = Vec::with_capacity(runs.len());
+    for (index, item) in runs.into_iter().enumerate() {
+        handles.push(build_thread(index, item, tx.clone()));
+    }
+    drop(tx);
+
+    // we will block ourselves waiting on events & be scheduled off
+    let mut interrupted = false;
+    loop {
+        if !interrupted && interrupt::interrupted().is_some() {
+            interrupted = true;
+            progress.clear();
+            eprintln!("trollrun: interrupted, stopping troll");
+            interrupt::kill_children();
+        }
+        match rx.recv_timeout(TICK) {
+            Ok(Event::Started(index)) => progress.started(index),
+            Ok(Event::Finished(index, output)) => {
+                // failures are reported on stderr, keep them off the status line
+                progress.clear();
+                reports[index] = Some(output.report());
+                let result = output.into_result();
+                if let Option::Some(ref item) = result {
+                    on_result(item);
+                }
+                progress.finished(index, result.is_some());
+                output_data[index] = result;
+            }
+            Err(RecvTimeoutError::Timeout) => progress.tick(),
+            Err(RecvTimeoutError::Disconnected) => break,
         }
+    }
+    progress.done();
 
-        // we will block ourselves once we try to join and be scheduled off
-
-        // collect their output
-        handles
-            .into_iter()
-            .filter_map(|handle| handle.join().ok())
-            .filter_map(|output| output)
-            .collect()
-    };
-    writer
-        .serialize_output(output_data, config.behavior)
-        .unwrap();
+    for handle in handles {
+        let _ = handle.join();
+    }
+    Batch {
+        data: output_data.into_iter().flatten().collect(),
+        reports: reports.into_iter().flatten().collect(),
+        interrupted,
+    }
 }
 
-fn build_thread(arg: TrollRun) -> JoinHandle<Option<TrollRecordable>> {
+fn build_thread(index: usize, arg: TrollRun, tx: Sender<Event>) -> JoinHandle<()> {
     Builder::new()
         .name(format!("trollrun_{}", &arg.name))
         .stack_size(1024 * 1024)
-        .spawn(move || arg.run().into_result())
+        .spawn(move || {
+            let _ = tx.send(Event::Started(index));
+            let _ = tx.send(Event::Finished(index, Box::new(arg.run())));
+        })
         .unwrap()
 }
@@ -0,0 +1,32 @@
+use std::fs::read;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// fnv1a is a simple & stable hash.
+///
+/// The standard library's hasher is free to change between releases,
+/// these values are written to disk so they need to stay put.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// hashes the contents of a file, returns `None` if it cannot be read
+pub fn hash_file(path: &str) -> Option<String> {
+    read(path)
+        .ok()
+        .map(|bytes| format!("{:016x}", fnv1a(&bytes)))
+}
+
+#[test]
+fn test_fnv1a() {
+    // reference values from the FNV specification
+    assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+use std::io::{Error, ErrorKind, Result};
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Mutex;
+
+#[cfg(unix)]
+use super::super::libc;
+
+/// the signal trollrun was interrupted by, zero if it hasn't been
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+lazy_static! {
+    /// the troll processes which are still running
+    static ref CHILDREN: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+}
+
+/// install catches Ctrl-C (& `SIGTERM`), so troll can be stopped & partial results kept.
+///
+/// The handler only records the signal, everything else happens on the main thread.
+/// A second Ctrl-C exits immediately.
+#[cfg(unix)]
+pub fn install() {
+    for signal in &[libc::SIGINT, libc::SIGTERM] {
+        unsafe {
+            let mut action: libc::sigaction = ::std::mem::zeroed();
+            action.sa_sigaction = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(*signal, &action, ::std::ptr::null_mut());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
+
+#[cfg(unix)]
+extern "C" fn handler(signal: libc::c_int) {
+    if SIGNAL.swap(signal, Ordering::SeqCst) != 0 {
+        unsafe { libc::_exit(128 + signal) };
+    }
+}
+
+/// the signal trollrun was interrupted by, if it was
+pub fn interrupted() -> Option<i32> {
+    match SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal),
+    }
+}
+
+/// the conventional exit status of a process killed by the signal
+pub fn exit_code() -> i32 {
+    128 + interrupted().unwrap_or(0)
+}
+
+/// spawn starts troll & remembers it, so it can be killed when interrupted.
+///
+/// Nothing new is started after an interrupt.
+pub fn spawn(cmd: &mut Command) -> Result<Child> {
+    let mut children = CHILDREN.lock().unwrap_or_else(|e| e.into_inner());
+    if interrupted().is_some() {
+        return Err(Error::new(
+            ErrorKind::Interrupted,
+            "trollrun was interrupted",
+        ));
+    }
+    let child = cmd.spawn()?;
+    children.insert(child.id());
+    Ok(child)
+}
+
+/// forgets a child, this has to happen before it is reaped so its pid isn't reused
+pub fn exited(pid: u32) {
+    let mut children = CHILDREN.lock().unwrap_or_else(|e| e.into_inner());
+    children.remove(&pid);
+}
+
+/// kill_children terminates every troll process which is still running
+#[cfg(unix)]
+pub fn kill_children() {
+    let children = CHILDREN.lock().unwrap_or_else(|e| e.into_inner());
+    for pid in children.iter() {
+        unsafe {
+            libc::kill(*pid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn kill_children() {}
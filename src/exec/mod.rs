@@ -1,5 +1,11 @@
 pub mod config;
 pub mod data;
+pub mod dryrun;
 pub mod exec;
+pub mod hash;
+pub mod interrupt;
 pub mod parser;
+pub mod progress;
 pub mod runs;
+pub mod template;
+pub mod usage;
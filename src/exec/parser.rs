@@ -1,114 +1,228 @@
-use super::super::regex::{Match, Regex};
-use std::borrow::Cow;
+use super::super::regex::Regex;
+use super::super::serde::{Deserialize, Serialize};
+use std::fmt;
 use std::io;
-use std::str::FromStr;
 
 lazy_static! {
-    static ref TROLL_LINE: Regex =
-        Regex::new(r#"^\s*(\d*\.?E?~?\d*):\s*(\d+\.?\d*E?~?\d*)\s*(\d+\.?\d*E?~?\d*)\s*$"#)
-            .unwrap();
-    static ref NUM: Regex = Regex::new(r#"^(\d*)\.?(\d*)?E?~?(\d*)?$"#).unwrap();
+    // the outcome is a number, a collection of numbers (space separated, or in braces),
+    // quoted text, or a pair
+    static ref TROLL_LINE: Regex = Regex::new(
+        r#"^\s*((?:[+~-]?\d+(?:\s+[+~-]?\d+)*)?|\{[\s\d,+~-]*\}|"[^"]*"|\[.*\])\s*:\s*([~+.\-\d]\S*)(?:\s+([~+.\-\d]\S*))?\s*$"#
+    )
+    .unwrap();
+    static ref INTEGER: Regex = Regex::new(r#"^([+~-]?)(\d+)$"#).unwrap();
+    static ref REAL: Regex =
+        Regex::new(r#"^([+~-]?)(\d*)(?:\.(\d+))?(?:[eE]([+~-]?)(\d+))?$"#).unwrap();
+    static ref SPECIAL_REAL: Regex = Regex::new(r#"^(?i)([+~-]?)(inf|infinity|nan)$"#).unwrap();
+    static ref SUMMARY_LINE: Regex = Regex::new(
+        r#"^\s*Average\s*=\s*(\S+)\s+Spread\s*=\s*(\S+)\s+Mean deviation\s*=\s*(\S+)\s*$"#
+    )
+    .unwrap();
+}
+
+/// Outcome is a single result a troll program can yield.
+///
+/// Most programs yield a number (which may be negative, `~3`), but troll can
+/// also yield a collection of numbers (`{1,3,5}`), text, or a pair. Pairs
+/// (`[1, "hit"]`) are kept as text, exactly as troll printed them.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Outcome {
+    Integer(i64),
+    Multiset(Vec<i64>),
+    Text(String),
+}
+impl Outcome {
+    /// parses the outcome at the start of a line of troll's output
+    pub fn new(arg: &str) -> Outcome {
+        let arg = arg.trim();
+        if let Option::Some(value) = parse_integer(arg) {
+            return Outcome::Integer(value);
+        }
+        if arg.starts_with('"') && arg.ends_with('"') && arg.len() >= 2 {
+            return Outcome::Text(arg[1..arg.len() - 1].to_string());
+        }
+        // troll writes collections space separated, but braces are accepted too
+        let elements = if arg.starts_with('{') && arg.ends_with('}') {
+            arg[1..arg.len() - 1]
+                .split(',')
+                .map(|element| element.trim())
+                .filter(|element| !element.is_empty())
+                .map(parse_integer)
+                .collect::<Option<Vec<i64>>>()
+        } else {
+            arg.split_whitespace()
+                .map(parse_integer)
+                .collect::<Option<Vec<i64>>>()
+        };
+        match elements {
+            Option::Some(mut elements) => {
+                elements.sort();
+                Outcome::Multiset(elements)
+            }
+            Option::None => Outcome::Text(arg.to_string()),
+        }
+    }
+
+    /// the value of an integer outcome
+    pub fn integer(&self) -> Option<i64> {
+        match self {
+            Outcome::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Integer(value) => write!(f, "{}", value),
+            Outcome::Multiset(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "{{{}}}", elements.join(","))
+            }
+            Outcome::Text(text) => write!(f, "{}", text),
+        }
+    }
 }
 
 /// TrollLine returns a line of tests of troll testing
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct TrollLine {
-    pub base_value: usize,
+    pub outcome: Outcome,
     pub prob: f64,
     pub accum: f64,
 }
 impl TrollLine {
-    pub fn with_base_and_accum(base_value: usize, accum: f64) -> Self {
+    pub fn with_base_and_accum(base_value: i64, accum: f64) -> Self {
         Self {
-            base_value,
+            outcome: Outcome::Integer(base_value),
             accum,
             prob: 0f64,
         }
     }
     pub fn zero() -> Self {
         Self {
-            base_value: 0,
+            outcome: Outcome::Integer(0),
             prob: 0f64,
             accum: 0f64,
         }
     }
-    /// new will construct a new value
+    /// new will construct a new value, if the line is part of the distribution
     pub fn new(arg: &str) -> Option<TrollLine> {
-        TROLL_LINE
-            .captures(arg)
-            .into_iter()
-            .flat_map(|caps| {
-                reformat::<_, usize>(caps.get(1))
-                    .into_iter()
-                    .zip(reformat::<_, f64>(caps.get(2)))
-                    .zip(reformat::<_, f64>(caps.get(3)))
-            })
-            .map(|((a, b), c)| TrollLine {
-                base_value: a,
-                prob: b,
-                accum: c,
-            })
-            .next()
-    }
-}
-
-// format handles string formatting
-#[inline(always)]
-fn reformat<'b, I, F>(iter: I) -> Option<F>
-where
-    I: IntoIterator<Item = Match<'b>>,
-    F: FromStr,
-{
-    iter.into_iter()
-        .filter(|item| !item.as_str().is_empty())
-        .filter_map(|item| NUM.captures(item.as_str()))
-        .filter_map(|caps| match (caps.get(1), caps.get(2), caps.get(3)) {
-            (Option::Some(ref a), Option::None, Option::None) if !a.as_str().is_empty() => {
-                Some(Cow::Borrowed(a.as_str()))
-            }
-            (Option::Some(ref a), Option::Some(ref b), Option::None)
-                if !a.as_str().is_empty() && b.as_str().is_empty() =>
-            {
-                Some(Cow::Borrowed(a.as_str()))
-            }
-            (Option::Some(ref a), Option::Some(ref b), Option::Some(ref c))
-                if !a.as_str().is_empty() && b.as_str().is_empty() && c.as_str().is_empty() =>
-            {
-                Some(Cow::Borrowed(a.as_str()))
-            }
+        TrollLine::parse(arg).ok().and_then(|line| line)
+    }
 
-            (Option::Some(ref a), Option::Some(ref b), Option::None)
-                if !a.as_str().is_empty() && !b.as_str().is_empty() =>
-            {
-                Some(Cow::Owned(format!("{}.{}", a.as_str(), b.as_str())))
-            }
-            (Option::Some(ref a), Option::Some(ref b), Option::Some(ref c))
-                if !a.as_str().is_empty() && !b.as_str().is_empty() && c.as_str().is_empty() =>
-            {
-                Some(Cow::Owned(format!("{}.{}", a.as_str(), b.as_str())))
-            }
+    /// parse reads a line of troll's distribution.
+    ///
+    /// Lines which aren't part of the distribution are `Ok(None)`, lines which look
+    /// like they are (an outcome, a colon, & numbers) but can't be read are an error.
+    /// Outcomes which aren't numbers may not have an accumulated probability,
+    /// it is recomputed when the lines are collected anyway.
+    pub fn parse(arg: &str) -> Result<Option<TrollLine>, String> {
+        let caps = match TROLL_LINE.captures(arg) {
+            Option::Some(caps) => caps,
+            Option::None => return Ok(None),
+        };
+        let prob = parse_real(&caps[2])
+            .ok_or_else(|| format!("probability:'{}' is not a number", &caps[2]))?;
+        let accum = match caps.get(3) {
+            Option::Some(accum) => parse_real(accum.as_str()).ok_or_else(|| {
+                format!(
+                    "accumulated probability:'{}' is not a number",
+                    accum.as_str()
+                )
+            })?,
+            Option::None => 0f64,
+        };
+        Ok(Some(TrollLine {
+            outcome: Outcome::new(&caps[1]),
+            prob,
+            accum,
+        }))
+    }
+}
 
-            (Option::Some(ref a), Option::Some(ref b), Option::Some(ref c))
-                if !a.as_str().is_empty() && !b.as_str().is_empty() && !c.as_str().is_empty() =>
-            {
-                Some(Cow::Owned(format!(
-                    "{}.{}e-{}",
-                    a.as_str(),
-                    b.as_str(),
-                    c.as_str()
-                )))
-            }
-            (_, _, _) => None,
+/// TrollSummary is the statistics troll prints after the distribution
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrollSummary {
+    pub average: f64,
+    pub spread: f64,
+    pub mean_deviation: f64,
+}
+impl TrollSummary {
+    /// parses the `Average = .. Spread = .. Mean deviation = ..` line
+    pub fn new(arg: &str) -> Option<TrollSummary> {
+        let caps = SUMMARY_LINE.captures(arg)?;
+        Some(TrollSummary {
+            average: parse_real(&caps[1])?,
+            spread: parse_real(&caps[2])?,
+            mean_deviation: parse_real(&caps[3])?,
         })
-        .filter_map(|arg| <F as FromStr>::from_str(&arg).ok())
-        .next()
+    }
+}
+
+/// parse_real reads a number the way Moscow ML (which troll is written in) writes them.
+///
+/// This follows the grammar of `Real.scan`: negative numbers & exponents use `~`
+/// (`-` is accepted too), either the integer or the fraction may be missing, but
+/// not both, & the exponent is optional (`1.5E~7`, `~0.5`, `2E10`, `.5`). `inf`
+/// & `nan` are accepted as well.
+fn parse_real(arg: &str) -> Option<f64> {
+    if let Option::Some(caps) = SPECIAL_REAL.captures(arg) {
+        let value = if caps[2].eq_ignore_ascii_case("nan") {
+            f64::NAN
+        } else {
+            f64::INFINITY
+        };
+        return Some(if negative(&caps[1]) { -value } else { value });
+    }
+    let caps = REAL.captures(arg)?;
+    let integer = caps.get(2).map(|m| m.as_str()).unwrap_or("");
+    let fraction = caps.get(3).map(|m| m.as_str()).unwrap_or("");
+    if integer.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    // rebuilt in rust's syntax, so rounding is left to the standard library
+    let mut rust = String::with_capacity(arg.len() + 4);
+    if negative(&caps[1]) {
+        rust.push('-');
+    }
+    rust.push_str(if integer.is_empty() { "0" } else { integer });
+    if !fraction.is_empty() {
+        rust.push('.');
+        rust.push_str(fraction);
+    }
+    if let Option::Some(exponent) = caps.get(5) {
+        rust.push('e');
+        if negative(caps.get(4).map(|m| m.as_str()).unwrap_or("")) {
+            rust.push('-');
+        }
+        rust.push_str(exponent.as_str());
+    }
+    rust.parse::<f64>().ok()
+}
+
+/// parse_integer reads a whole number, negative numbers use `~` like `parse_real`
+fn parse_integer(arg: &str) -> Option<i64> {
+    let caps = INTEGER.captures(arg)?;
+    let value = caps[2].parse::<i64>().ok()?;
+    Some(if negative(&caps[1]) { -value } else { value })
+}
+
+fn negative(sign: &str) -> bool {
+    sign == "~" || sign == "-"
+}
+
+/// parses a line of troll's random roll output, these are a single number
+pub fn parse_sample(arg: &str) -> Option<i64> {
+    parse_integer(arg.trim())
 }
 
 #[test]
 fn non_expodential_test() {
     let dut = "     37:    0.0151748971193     0.0388695987654";
     let out = TrollLine::new(dut).unwrap();
-    assert_eq!(out.base_value, 37);
+    assert_eq!(out.outcome, Outcome::Integer(37));
     assert_eq!(out.prob, 0.0151748971193f64);
     assert_eq!(out.accum, 0.0388695987654f64);
 }
@@ -117,11 +231,166 @@ fn non_expodential_test() {
 fn expodential_test() {
     let dut = "     45:    7.14449016918E~5    9.82367398262E~5  ";
     let out = TrollLine::new(dut).unwrap();
-    assert_eq!(out.base_value, 45);
+    assert_eq!(out.outcome, Outcome::Integer(45));
     assert_eq!(out.prob, 7.14449016918e-5f64);
     assert_eq!(out.accum, 9.82367398262e-5f64);
 }
 
+#[test]
+fn outcome_test() {
+    let dut = "  1 3 5 :    12.5    ";
+    let out = TrollLine::new(dut).unwrap();
+    assert_eq!(out.outcome, Outcome::Multiset(vec![1, 3, 5]));
+    assert_eq!(out.prob, 12.5);
+
+    assert_eq!(Outcome::new("{5, 1,3}"), Outcome::Multiset(vec![1, 3, 5]));
+    assert_eq!(Outcome::new("{}"), Outcome::Multiset(vec![]));
+    assert_eq!(Outcome::new("\"hit\""), Outcome::Text("hit".to_string()));
+    assert_eq!(Outcome::new("[1, 2]"), Outcome::Text("[1, 2]".to_string()));
+    assert_eq!(Outcome::new("{5, 1,3}").to_string(), "{1,3,5}");
+    assert_eq!(Outcome::new("~3"), Outcome::Integer(-3));
+    assert_eq!(Outcome::new("-3"), Outcome::Integer(-3));
+    assert_eq!(Outcome::new("2 ~1"), Outcome::Multiset(vec![-1, 2]));
+    assert_eq!(Outcome::new("{~1, 2}").to_string(), "{-1,2}");
+    assert_eq!(Outcome::new("~~3"), Outcome::Text("~~3".to_string()));
+    assert_eq!(parse_sample(" ~3 "), Some(-3));
+    assert_eq!(parse_sample("{1,2}"), None);
+
+    let dut = "    ~12:    2.5     2.5";
+    let out = TrollLine::new(dut).unwrap();
+    assert_eq!(out.outcome, Outcome::Integer(-12));
+
+    let dut = "  [1 3, \"hit\"]:    50.0";
+    let out = TrollLine::new(dut).unwrap();
+    assert_eq!(out.outcome, Outcome::Text("[1 3, \"hit\"]".to_string()));
+    assert_eq!(out.prob, 50.0);
+
+    let dut = "  \"critical: hit\":    2.5     2.5";
+    let out = TrollLine::new(dut).unwrap();
+    assert_eq!(out.outcome, Outcome::Text("critical: hit".to_string()));
+    assert_eq!(out.accum, 2.5);
+}
+
+#[test]
+fn summary_test() {
+    let dut = "Average = 2.72222222222    Spread = 3.38281179463  Mean deviation = 2.77395719641";
+    let out = TrollSummary::new(dut).unwrap();
+    assert_eq!(out.average, 2.72222222222);
+    assert_eq!(out.spread, 3.38281179463);
+    assert_eq!(out.mean_deviation, 2.77395719641);
+
+    let dut = "Average = ~1.5    Spread = 2.5E~3  Mean deviation = 1.0";
+    let out = TrollSummary::new(dut).unwrap();
+    assert_eq!(out.average, -1.5);
+    assert_eq!(out.spread, 2.5e-3);
+    assert!(TrollSummary::new("     37:    0.0151748971193     0.0388695987654").is_none());
+}
+
+#[test]
+fn real_test() {
+    let accepted: &[(&str, f64)] = &[
+        ("0", 0.0),
+        ("100.0", 100.0),
+        ("45.419654612", 45.419654612),
+        ("~0.5", -0.5),
+        ("-0.5", -0.5),
+        ("+2.5", 2.5),
+        (".5", 0.5),
+        ("~.5", -0.5),
+        ("1.2E5", 1.2e5),
+        ("1.2e5", 1.2e5),
+        ("1.2E+5", 1.2e5),
+        ("1.2E~5", 1.2e-5),
+        ("1.2E-5", 1.2e-5),
+        ("7E~05", 7e-5),
+        ("~1E~3", -1e-3),
+        ("4.30195798619E~8", 4.30195798619e-8),
+        ("1E400", f64::INFINITY),
+        ("inf", f64::INFINITY),
+        ("~inf", f64::NEG_INFINITY),
+        ("Infinity", f64::INFINITY),
+    ];
+    for (dut, expected) in accepted {
+        assert_eq!(parse_real(dut), Some(*expected), "parsing:'{}'", dut);
+    }
+    assert!(parse_real("nan").unwrap().is_nan());
+    assert!(parse_real("~nan").unwrap().is_nan());
+
+    let rejected = &[
+        "", "~", "-", ".", "1.", "E5", "1E", "1E~", "1.2.3", "~~1", "1E5.0", "1,5", "0x10", "abc",
+        " 1", "1 ", "1E~ 5", "infinite",
+    ];
+    for dut in rejected {
+        assert_eq!(parse_real(dut), None, "parsing:'{}'", dut);
+    }
+}
+
+#[test]
+fn real_round_trip_test() {
+    // moscow ml's syntax, produced from the shortest representation which round trips
+    let mosml = |x: f64| format!("{:e}", x).replace('-', "~").replace('e', "E");
+    let plain = |x: f64| format!("{}", x).replace('-', "~");
+
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut checked = 0;
+    while checked < 20_000 {
+        // xorshift, so every run checks the same values
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let x = f64::from_bits(state);
+        if !x.is_finite() {
+            continue;
+        }
+        assert_eq!(
+            parse_real(&mosml(x)).map(f64::to_bits),
+            Some(x.to_bits()),
+            "{}",
+            mosml(x)
+        );
+        if x.abs() > 1e-9 && x.abs() < 1e9 {
+            assert_eq!(
+                parse_real(&plain(x)).map(f64::to_bits),
+                Some(x.to_bits()),
+                "{}",
+                plain(x)
+            );
+        }
+        checked += 1;
+    }
+}
+
+#[test]
+fn unparsed_line_test() {
+    let out = TrollLine::parse("     3:    1.2E5     ~0.5")
+        .unwrap()
+        .unwrap();
+    assert_eq!(out.prob, 1.2e5);
+    assert_eq!(out.accum, -0.5);
+
+    assert!(TrollLine::parse("     3:    1.2.3     4.0").is_err());
+    assert!(TrollLine::parse("     3:    1.0     4.0E").is_err());
+    assert_eq!(
+        TrollLine::parse("3: 1.2.3 4.0").err(),
+        Some("probability:'1.2.3' is not a number".to_string())
+    );
+
+    // lines which aren't data are skipped without complaint
+    for dut in &[
+        "",
+        "Average = 2.5 Spread = 1.0 Mean deviation = 1.0",
+        "Line 3: unbound x",
+        "Runtime error: 12",
+        "at 3: 4.0 5.0",
+    ] {
+        assert!(
+            TrollLine::parse(dut).unwrap().is_none(),
+            "parsing:'{}'",
+            dut
+        );
+    }
+}
+
 #[test]
 fn lol_wtf() {
     let dut = include_str!("dda_test_run_output");
@@ -129,7 +398,7 @@ fn lol_wtf() {
     assert_eq!(output.len(), 37);
 
     // check a few cases
-    assert_eq!(output[0].base_value, 0);
+    assert_eq!(output[0].outcome, Outcome::Integer(0));
     assert_eq!(output[0].prob, 45.419654612);
     assert_eq!(output[0].accum, 100.0);
 }
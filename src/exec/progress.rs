@@ -0,0 +1,153 @@
+use std::cmp::Reverse;
+use std::io::{stderr, Write};
+use std::time::{Duration, Instant};
+
+use super::super::atty::{self, Stream};
+
+/// how many of the longest running runs are named on the status line
+const SLOWEST_SHOWN: usize = 3;
+
+#[derive(Clone, Copy)]
+enum RunState {
+    Queued,
+    Running(Instant),
+    Done(Duration),
+    Failed(Duration),
+}
+
+/// Progress reports how a batch is going on stderr.
+///
+/// When stderr is a terminal a single status line is redrawn in place,
+/// otherwise every start & finish is logged on its own line.
+pub struct Progress {
+    enabled: bool,
+    tty: bool,
+    start: Instant,
+    names: Vec<String>,
+    states: Vec<RunState>,
+}
+impl Progress {
+    pub fn new(names: Vec<String>, enabled: bool) -> Progress {
+        let states = names.iter().map(|_| RunState::Queued).collect();
+        Progress {
+            enabled,
+            tty: atty::is(Stream::Stderr),
+            start: Instant::now(),
+            names,
+            states,
+        }
+    }
+
+    /// a run has started executing
+    pub fn started(&mut self, index: usize) {
+        self.states[index] = RunState::Running(Instant::now());
+        if self.enabled && !self.tty {
+            eprintln!("trollrun: started {}", &self.names[index]);
+        }
+        self.redraw();
+    }
+
+    /// a run has finished, `ok` is false if it failed
+    pub fn finished(&mut self, index: usize, ok: bool) {
+        let elapsed = match self.states[index] {
+            RunState::Running(since) => since.elapsed(),
+            _ => Duration::from_secs(0),
+        };
+        self.states[index] = if ok {
+            RunState::Done(elapsed)
+        } else {
+            RunState::Failed(elapsed)
+        };
+        if self.enabled && !self.tty {
+            eprintln!(
+                "trollrun: {} {} after {:.2}s",
+                if ok { "finished" } else { "failed" },
+                &self.names[index],
+                seconds(&elapsed)
+            );
+        }
+        self.redraw();
+    }
+
+    /// refreshes the status line, so running times keep counting up
+    pub fn tick(&mut self) {
+        self.redraw();
+    }
+
+    /// removes the status line so other messages don't get mangled
+    pub fn clear(&mut self) {
+        if self.enabled && self.tty {
+            eprint!("\r\x1b[K");
+        }
+    }
+
+    /// the batch is over, write the final tally
+    pub fn done(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.clear();
+        let (_, _, done, failed) = self.counts();
+        eprintln!(
+            "trollrun: {} done, {} failed in {:.2}s",
+            done,
+            failed,
+            seconds(&self.start.elapsed())
+        );
+    }
+
+    fn counts(&self) -> (usize, usize, usize, usize) {
+        let mut counts = (0, 0, 0, 0);
+        for state in self.states.iter() {
+            match state {
+                RunState::Queued => counts.0 += 1,
+                RunState::Running(_) => counts.1 += 1,
+                RunState::Done(_) => counts.2 += 1,
+                RunState::Failed(_) => counts.3 += 1,
+            }
+        }
+        counts
+    }
+
+    fn redraw(&mut self) {
+        if !self.enabled || !self.tty {
+            return;
+        }
+        let (queued, running, done, failed) = self.counts();
+        let mut line = format!(
+            "[{:.1}s] queued {} | running {} | done {} | failed {}",
+            seconds(&self.start.elapsed()),
+            queued,
+            running,
+            done,
+            failed
+        );
+
+        // name the runs which have been going the longest
+        let mut slowest: Vec<(Duration, &str)> = self
+            .states
+            .iter()
+            .zip(self.names.iter())
+            .filter_map(|(state, name)| match state {
+                RunState::Running(since) => Some((since.elapsed(), name.as_str())),
+                _ => None,
+            })
+            .collect();
+        slowest.sort_by_key(|&(elapsed, _)| Reverse(elapsed));
+        if !slowest.is_empty() {
+            let shown: Vec<String> = slowest
+                .iter()
+                .take(SLOWEST_SHOWN)
+                .map(|(elapsed, name)| format!("{} ({:.1}s)", name, seconds(elapsed)))
+                .collect();
+            line.push_str(" | slowest: ");
+            line.push_str(&shown.join(", "));
+        }
+        eprint!("\r\x1b[K{}", line);
+        let _ = stderr().flush();
+    }
+}
+
+fn seconds(d: &Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_millis()) / 1000.0
+}
@@ -1,23 +1,147 @@
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, remove_file, write};
 use std::io::Error;
-use std::process::Command;
+use std::path::PathBuf;
+use std::process::{Command, Output};
 
 use super::super::cli::StatBehavior;
+use super::super::regex::Regex;
+use super::super::serde::{Deserialize, Serialize};
+use super::config::{add_mmap, resolve};
 use super::data::TrollData;
-use super::parser::TrollLine;
+use super::hash::{fnv1a, hash_file};
+use super::interrupt::interrupted;
+use super::parser::{parse_sample, TrollLine, TrollSummary};
+use super::template::expand;
+use super::usage::{output_with_usage, RunTiming};
+
+lazy_static! {
+    static ref OUT_OF_MEMORY: Regex =
+        Regex::new(r#"(?i)out of memory|cannot allocate memory|heap exhausted"#).unwrap();
+}
 
 /// TrollRun is a labled run of troll ready to be executed
 pub struct TrollRun {
     pub name: String,
+    pub label: Option<String>,
+    pub program: ProgramInfo,
+    pub samples: Option<usize>,
+    pub max_memory: Option<u64>,
+    /// was the mmap workaround applied up front
+    pub mmap: bool,
+    /// does troll start with an empty environment
+    pub env_clear: bool,
+    /// the program of the run, when it is given inline or templated
+    pub scratch: Option<ScratchProgram>,
+    /// does output which fails the sanity checks fail the run (`--strict`)
+    pub strict: bool,
     pub cmd: Command,
 }
 
+/// ScratchProgram is a troll program which has to be written to a
+/// scratch file before the run executes
+pub struct ScratchProgram {
+    pub path: PathBuf,
+    pub origin: ProgramSource,
+    pub substitute: BTreeMap<String, String>,
+}
+impl ScratchProgram {
+    /// the program troll will be given, after substitutions
+    pub fn render(&self) -> Result<String, RunResult> {
+        let template = match self.origin {
+            ProgramSource::Inline(ref source) => source.clone(),
+            ProgramSource::File(ref path) => read_to_string(path)?,
+        };
+        match expand(&template, &self.substitute) {
+            Ok(program) => Ok(program),
+            Err(e) => Err(RunResult::Error(e, String::new())),
+        }
+    }
+
+    /// writes the scratch file, returning the program that was written
+    fn write(&self) -> Result<String, RunResult> {
+        let program = self.render()?;
+        write(&self.path, &program)?;
+        Ok(program)
+    }
+}
+impl Drop for ScratchProgram {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.path);
+    }
+}
+
+/// ProgramSource is where the text of a scratch program comes from
+pub enum ProgramSource {
+    Inline(String),
+    File(String),
+}
+
+/// ProgramInfo describes which troll program a run executed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProgramInfo {
+    pub path: String,
+    pub args: BTreeMap<String, usize>,
+    pub hash: Option<String>,
+    /// the program itself, for runs with an inline `source`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// the `{{NAME}}` placeholders which were replaced
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub substitute: BTreeMap<String, String>,
+    /// environment variables set for troll
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// the working directory of troll, relative paths are resolved from here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// the name of the troll executable from `[troll.bins]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin: Option<String>,
+    /// was troll executed with the mmap workaround
+    pub mmap: bool,
+}
+
 /// TrollOutput describes what happened
 pub struct TrollOutput {
     pub name: String,
+    pub label: Option<String>,
+    pub program: ProgramInfo,
     pub result: RunResult,
+    pub timing: RunTiming,
+    /// did the run run out of memory, and get retried with the mmap workaround
+    pub retried: bool,
 }
 impl TrollOutput {
+    /// a run an earlier batch completed, checked again like it was just executed
+    pub fn resumed(item: TrollRecordable, strict: bool) -> TrollOutput {
+        TrollOutput {
+            name: item.name,
+            label: item.label,
+            program: item.program,
+            result: RunResult::Success(item.result).checked(strict),
+            timing: RunTiming::default(),
+            retried: false,
+        }
+    }
+
+    /// summarizes how the run went, this is kept for failed runs too
+    pub fn report(&self) -> RunReport {
+        RunReport {
+            name: self.name.clone(),
+            status: self.result.status(),
+            timing: self.timing,
+            retried: self.retried,
+        }
+    }
+
     pub fn into_result(self) -> Option<TrollRecordable> {
+        if self.retried {
+            eprintln!(
+                "{} ran out of memory, it was retried with the mmap workaround (mmap_override).",
+                self.name
+            );
+        }
         match self.result {
             RunResult::ExecErr(e) => {
                 eprintln!("{} failed to exec with error:'{:?}'", self.name, e);
@@ -27,21 +151,53 @@ impl TrollOutput {
                 eprintln!("{} failed.\n{}\n{}\n", self.name, stdout, stderr);
                 None
             }
-            RunResult::Success(data) => Some(TrollRecordable {
-                name: self.name,
-                result: data,
-            }),
+            RunResult::OutOfMemory(stdout, stderr) => {
+                eprintln!("{} ran out of memory.\n{}\n{}\n", self.name, stdout, stderr);
+                None
+            }
+            RunResult::Interrupted => {
+                eprintln!("{} was interrupted.", self.name);
+                None
+            }
+            RunResult::Invalid(warnings) => {
+                eprintln!("{} failed the sanity checks (--strict).", self.name);
+                for warning in warnings {
+                    eprintln!("{} error, {}", self.name, warning);
+                }
+                None
+            }
+            RunResult::Success(data) => {
+                for warning in data.warnings() {
+                    eprintln!("{} warning, {}", self.name, warning);
+                }
+                Some(TrollRecordable {
+                    name: self.name,
+                    label: self.label,
+                    program: self.program,
+                    result: data,
+                })
+            }
         }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TrollRecordable {
     pub name: String,
+    pub label: Option<String>,
+    pub program: ProgramInfo,
+    #[serde(rename = "data")]
     pub result: TrollData,
 }
 impl TrollRecordable {
-    pub fn split(self, behavior: StatBehavior) -> (String, Vec<f64>) {
-        let name = self.name;
+    /// the column header for this run, the label if one was given otherwise the name
+    pub fn header(&self) -> &str {
+        header(&self.name, &self.label)
+    }
+
+    /// split the data into the columns written to the output, see `columns`
+    pub fn split(self, behavior: StatBehavior) -> Vec<(String, Vec<f64>)> {
+        let names = columns(&self.name, &self.label, self.result.samples().is_some());
         let mut data = Vec::with_capacity(self.result.len());
         for index in 0..self.result.len() {
             // what we output depends on our behavior enum
@@ -54,7 +210,52 @@ impl TrollRecordable {
                 }
             }
         }
-        (name, data)
+        if self.result.samples().is_none() {
+            return names.into_iter().zip(vec![data]).collect();
+        }
+        let mut low = Vec::with_capacity(data.len());
+        let mut high = Vec::with_capacity(data.len());
+        for value in data.iter() {
+            let (l, h) = self.result.interval(*value).unwrap_or((*value, *value));
+            low.push(l);
+            high.push(h);
+        }
+        names.into_iter().zip(vec![data, low, high]).collect()
+    }
+}
+
+fn header<'a>(name: &'a str, label: &'a Option<String>) -> &'a str {
+    match label {
+        Option::Some(ref label) => label,
+        Option::None => name,
+    }
+}
+
+/// the names of the columns a run is written as.
+///
+/// Sampled runs are followed by the bounds of their 95% confidence interval.
+pub fn columns(name: &str, label: &Option<String>, sampled: bool) -> Vec<String> {
+    let name = header(name, label).to_string();
+    if !sampled {
+        return vec![name];
+    }
+    let low = format!("{} (95% low)", &name);
+    let high = format!("{} (95% high)", &name);
+    vec![name, low, high]
+}
+
+/// RunReport is what is known about a run, regardless of if it succeeded
+#[derive(Clone, Debug, Serialize)]
+pub struct RunReport {
+    pub name: String,
+    pub status: &'static str,
+    pub timing: RunTiming,
+    pub retried: bool,
+}
+impl RunReport {
+    /// did `--strict` fail the run
+    pub fn is_invalid(&self) -> bool {
+        self.status == "invalid"
     }
 }
 
@@ -62,6 +263,11 @@ impl TrollRecordable {
 pub enum RunResult {
     ExecErr(Error),
     Error(String, String),
+    OutOfMemory(String, String),
+    /// trollrun was interrupted before the run finished
+    Interrupted,
+    /// troll's output failed the sanity checks, & `--strict` was given
+    Invalid(Vec<String>),
     Success(TrollData),
 }
 impl From<Error> for RunResult {
@@ -80,13 +286,52 @@ impl<'a> From<&'a [u8]> for RunResult {
     }
 }
 impl RunResult {
+    /// with `--strict` output which fails the sanity checks is invalid
+    fn checked(self, strict: bool) -> RunResult {
+        match self {
+            RunResult::Success(data) if strict => match data.warnings() {
+                ref warnings if warnings.is_empty() => RunResult::Success(data),
+                warnings => RunResult::Invalid(warnings),
+            },
+            result => result,
+        }
+    }
+
+    /// did `--strict` fail the run
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, RunResult::Invalid(_))
+    }
+
+    /// a short description of the outcome
+    pub fn status(&self) -> &'static str {
+        match self {
+            RunResult::ExecErr(_) => "exec-error",
+            RunResult::Error(_, _) => "failed",
+            RunResult::OutOfMemory(_, _) => "out-of-memory",
+            RunResult::Interrupted => "interrupted",
+            RunResult::Invalid(_) => "invalid",
+            RunResult::Success(_) => "ok",
+        }
+    }
+
     // build a run result from a yet to ran process
-    fn new(arg: Command) -> RunResult {
-        let mut arg = arg;
-        let output = match arg.output() {
-            Err(err) => return RunResult::from(err),
+    fn new(
+        arg: &mut Command,
+        samples: Option<usize>,
+        max_memory: Option<u64>,
+    ) -> (RunResult, RunTiming) {
+        let (output, timing) = match output_with_usage(arg) {
+            Err(err) => return (RunResult::from(err), RunTiming::default()),
             Ok(output) => output,
         };
+        (RunResult::parse(output, samples, max_memory), timing)
+    }
+
+    // interpret what troll wrote
+    fn parse(output: Output, samples: Option<usize>, max_memory: Option<u64>) -> RunResult {
+        if is_out_of_memory(&output, max_memory) {
+            return RunResult::OutOfMemory(to_string(&output.stdout), to_string(&output.stderr));
+        }
 
         // did the command succeed or fail?
         // troll doesn't use return codes, because of course not
@@ -97,25 +342,136 @@ impl RunResult {
                 String::from_utf8_lossy(output.stderr.as_slice()).to_string(),
             ),
         };
-        let lines: TrollData = stdout.lines().filter_map(TrollLine::new).collect();
+        if samples.is_some() {
+            let mut rolls = Vec::new();
+            let mut unparsed = Vec::new();
+            for line in stdout
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+            {
+                match parse_sample(line) {
+                    Option::Some(roll) => rolls.push(roll),
+                    Option::None => unparsed.push(format!("'{}' is not a sample", line)),
+                }
+            }
+            if rolls.is_empty() {
+                return RunResult::Error(stdout, stderr);
+            }
+            let mut data = TrollData::from_samples(&rolls);
+            data.set_unparsed(unparsed);
+            return RunResult::Success(data);
+        }
+        let mut parsed = Vec::new();
+        let mut unparsed = Vec::new();
+        for line in stdout.lines() {
+            match TrollLine::parse(line) {
+                Ok(Option::Some(line)) => parsed.push(line),
+                Ok(Option::None) => {}
+                Err(e) => unparsed.push(format!("'{}' {}", line.trim(), e)),
+            }
+        }
+        let mut lines: TrollData = parsed.into_iter().collect();
         if lines.is_empty() {
-            RunResult::Error(stdout, stderr)
-        } else {
-            RunResult::Success(lines)
+            return RunResult::Error(stdout, stderr);
         }
+        lines.set_unparsed(unparsed);
+        if let Option::Some(summary) = stdout.lines().filter_map(TrollSummary::new).next() {
+            lines.set_summary(summary);
+        }
+        RunResult::Success(lines)
     }
 }
 
 impl TrollRun {
     /// run handles converting the plan to execute into an actual execution
+    ///
+    /// A run which runs out of memory is retried once with the mmap workaround,
+    /// unless it was already applied.
     pub fn run(self) -> TrollOutput {
+        let mut cmd = self.cmd;
+        let mut program = self.program;
+        // the scratch file is removed once `scratch` is dropped
+        let scratch = self.scratch;
+        let written = match scratch {
+            Option::Some(ref scratch) => scratch.write().map(Some),
+            Option::None => Ok(None),
+        };
+        // hash what troll is actually given, after templating
+        program.hash = match written {
+            Ok(Option::Some(ref text)) => Some(format!("{:016x}", fnv1a(text.as_bytes()))),
+            Ok(Option::None) => hash_file(&resolve(&program.cwd, &program.path)),
+            Err(result) => {
+                return TrollOutput {
+                    name: self.name,
+                    label: self.label,
+                    program,
+                    result,
+                    timing: RunTiming::default(),
+                    retried: false,
+                };
+            }
+        };
+        let (mut result, mut timing) = RunResult::new(&mut cmd, self.samples, self.max_memory);
+        // only Moscow-ML's own heap runs out because of the mmap problem, the
+        // workaround doesn't lift `max_memory` so a crash under the limit isn't retried
+        let retried = match result {
+            RunResult::OutOfMemory(_, ref stderr)
+                if !self.mmap && interrupted().is_none() && OUT_OF_MEMORY.is_match(stderr) =>
+            {
+                add_mmap(&mut cmd);
+                let (r, t) = RunResult::new(&mut cmd, self.samples, self.max_memory);
+                result = r;
+                timing = t;
+                true
+            }
+            _ => false,
+        };
+        // troll was killed (or never started) because we were interrupted
+        if interrupted().is_some() {
+            result = match result {
+                RunResult::Success(data) => RunResult::Success(data),
+                _ => RunResult::Interrupted,
+            };
+        }
+        let result = result.checked(self.strict);
+        program.mmap = self.mmap || retried;
         TrollOutput {
             name: self.name,
-            result: RunResult::new(self.cmd),
+            label: self.label,
+            program,
+            result,
+            timing,
+            retried,
         }
     }
 }
 
+/// did the process die because it couldn't get more memory?
+///
+/// Moscow-ML reports this on stderr. When we imposed a memory limit, a failed
+/// allocation can also turn into a segfault or an abort, so those are blamed
+/// on the limit too. Other signals (e.g. a SIGKILL from the user) are not.
+fn is_out_of_memory(output: &Output, max_memory: Option<u64>) -> bool {
+    OUT_OF_MEMORY.is_match(&to_string(&output.stderr))
+        || (max_memory.is_some() && killed_by_allocation_failure(output))
+}
+
+#[cfg(unix)]
+fn killed_by_allocation_failure(output: &Output) -> bool {
+    use super::super::libc;
+    use std::os::unix::process::ExitStatusExt;
+    match output.status.signal() {
+        Option::Some(signal) => signal == libc::SIGSEGV || signal == libc::SIGABRT,
+        Option::None => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn killed_by_allocation_failure(_: &Output) -> bool {
+    false
+}
+
 fn to_string(slice: &[u8]) -> String {
     use std::str::from_utf8;
     match slice.len() {
@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+
+use super::super::regex::{Captures, Regex};
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex =
+        Regex::new(r#"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}"#).unwrap();
+}
+
+/// expand replaces every `{{NAME}}` placeholder in a troll program.
+///
+/// Placeholders without a substitution are an error, troll's own error
+/// message for a left over `{{` is not helpful.
+pub fn expand(template: &str, substitute: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut missing: Vec<String> = Vec::new();
+    let expanded =
+        PLACEHOLDER.replace_all(template, |caps: &Captures| match substitute.get(&caps[1]) {
+            Option::Some(value) => value.clone(),
+            Option::None => {
+                if !missing.iter().any(|name| name == &caps[1]) {
+                    missing.push(caps[1].to_string());
+                }
+                caps[0].to_string()
+            }
+        });
+    if missing.is_empty() {
+        Ok(expanded.into_owned())
+    } else {
+        Err(format!(
+            "no substitution for placeholder(s):'{}'",
+            missing.join("', '")
+        ))
+    }
+}
+
+#[test]
+fn test_expand() {
+    let mut substitute = BTreeMap::new();
+    substitute.insert("DIE".to_string(), "d8".to_string());
+    substitute.insert("OP".to_string(), "max".to_string());
+
+    assert_eq!(
+        expand("{{OP}} 3{{DIE}} + {{ DIE }}", &substitute).unwrap(),
+        "max 3d8 + d8"
+    );
+    assert_eq!(expand("sum 3d6", &substitute).unwrap(), "sum 3d6");
+    assert_eq!(
+        expand("{{HIT}} {{DIE}} {{HIT}}", &substitute).unwrap_err(),
+        "no substitution for placeholder(s):'HIT'"
+    );
+}
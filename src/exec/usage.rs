@@ -0,0 +1,157 @@
+use std::io::{Error, ErrorKind, Read, Result};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::super::serde::Serialize;
+use super::interrupt::{exited, spawn};
+
+#[cfg(unix)]
+use super::super::libc;
+
+/// RunTiming records how expensive a troll process was
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct RunTiming {
+    /// seconds between spawning & reaping the process
+    pub wall: f64,
+    /// seconds spent executing in user space
+    pub user_cpu: Option<f64>,
+    /// seconds spent executing in the kernel
+    pub system_cpu: Option<f64>,
+    /// peak resident set size in KiB
+    pub max_rss_kib: Option<u64>,
+}
+
+/// output_with_usage behaves like `Command::output` but also measures the process.
+///
+/// On unix the child is reaped with `wait4` so the kernel tells us its resource usage,
+/// elsewhere only the wall time is known.
+#[cfg(unix)]
+pub fn output_with_usage(cmd: &mut Command) -> Result<(Output, RunTiming)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let start = Instant::now();
+    let mut child = spawn(
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )?;
+    let pid = child.id() as libc::pid_t;
+
+    // both pipes have to be drained at the same time, or troll may block writing to one
+    let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+    let stderr_thread = thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(move || {
+            let mut buffer = Vec::new();
+            stderr_pipe.read_to_end(&mut buffer).map(|_| buffer)
+        })?;
+    let mut stdout = Vec::new();
+    child
+        .stdout
+        .take()
+        .expect("stdout is piped")
+        .read_to_end(&mut stdout)?;
+    let stderr = match stderr_thread.join() {
+        Ok(result) => result?,
+        Err(_) => return Err(Error::other("stderr reader panicked")),
+    };
+
+    // wait without reaping, so the pid can't be reused while it can still be killed
+    loop {
+        let mut info: libc::siginfo_t = unsafe { ::std::mem::zeroed() };
+        let ret = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if ret >= 0 {
+            break;
+        }
+        let err = Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    exited(pid as u32);
+
+    let mut status: libc::c_int = 0;
+    let mut usage: libc::rusage = unsafe { ::std::mem::zeroed() };
+    loop {
+        let ret = unsafe { libc::wait4(pid, &mut status, 0, &mut usage) };
+        if ret >= 0 {
+            break;
+        }
+        let err = Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+
+    let timing = RunTiming {
+        wall: seconds(&start.elapsed()),
+        user_cpu: Some(timeval_seconds(&usage.ru_utime)),
+        system_cpu: Some(timeval_seconds(&usage.ru_stime)),
+        max_rss_kib: Some(max_rss_kib(usage.ru_maxrss)),
+    };
+    let output = Output {
+        status: ::std::process::ExitStatus::from_raw(status),
+        stdout,
+        stderr,
+    };
+    Ok((output, timing))
+}
+
+#[cfg(not(unix))]
+pub fn output_with_usage(cmd: &mut Command) -> Result<(Output, RunTiming)> {
+    let start = Instant::now();
+    let child = spawn(
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )?;
+    let pid = child.id();
+    let output = child.wait_with_output();
+    exited(pid);
+    let output = output?;
+    let timing = RunTiming {
+        wall: seconds(&start.elapsed()),
+        ..RunTiming::default()
+    };
+    Ok((output, timing))
+}
+
+fn seconds(d: &Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_micros()) / 1_000_000.0
+}
+
+#[cfg(unix)]
+fn timeval_seconds(tv: &libc::timeval) -> f64 {
+    tv.tv_sec as f64 + tv.tv_usec as f64 / 1_000_000.0
+}
+
+/// linux reports `ru_maxrss` in KiB, macos reports it in bytes
+#[cfg(unix)]
+fn max_rss_kib(max_rss: libc::c_long) -> u64 {
+    if cfg!(target_os = "macos") {
+        max_rss as u64 / 1024
+    } else {
+        max_rss as u64
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_output_with_usage() {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg("echo out; echo err 1>&2");
+    let (output, timing) = output_with_usage(&mut cmd).unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"out\n");
+    assert_eq!(output.stderr, b"err\n");
+    assert!(timing.wall > 0.0);
+    assert!(timing.max_rss_kib.unwrap() > 0);
+}
@@ -2,30 +2,148 @@
 
 #[macro_use]
 extern crate lazy_static;
+extern crate atty;
 extern crate clap;
 extern crate csv;
+extern crate libc;
 extern crate regex;
+extern crate rusqlite;
+extern crate rust_xlsxwriter;
 extern crate serde;
+extern crate serde_json;
 extern crate toml;
 
+use std::collections::BTreeMap;
+
 pub mod cli;
 pub use self::cli::AppConfig;
 pub mod exec;
 use self::exec::config::RunKind;
+use self::exec::dryrun::describe;
 use self::exec::exec::run_program;
+use self::exec::interrupt::{exit_code, install};
+use self::exec::runs::{TrollOutput, TrollRecordable, TrollRun};
 pub mod marshal;
-use self::marshal::csv::config::CSVConfig;
+use self::marshal::journal::Journal;
+use self::marshal::output::{write_all, write_partial, OutputWriter};
+use self::marshal::stream::{open_stream, record};
+use self::marshal::timings::write_timings;
 pub mod unmarshal;
 use self::unmarshal::ConfigFormat;
+pub mod watch;
+use self::watch::watch;
 
 fn main() {
     // read OS flags
     let flags = AppConfig::default();
     // load config
     let config = ConfigFormat::new(&flags.file_path).expect("file was validated by cli");
-    let runs = config.build_runs();
-    // build our output format
-    let mut csv_config = CSVConfig::build_config(&config.csv);
-    run_program(runs, &mut csv_config, &flags);
-    csv_config.flush().unwrap();
+    if flags.watch {
+        install();
+        watch(&flags);
+    }
+    let runs = config.build_runs(flags.strict);
+    if flags.dry_run {
+        for run in runs.iter() {
+            println!("{}", describe(run));
+        }
+        return;
+    }
+    // check every output before running, so bad paths are found early
+    for output in config.outputs() {
+        if let Err(e) = output.check() {
+            eprintln!("{}", e);
+            ::std::process::exit(1);
+        }
+    }
+    install();
+    // the journal records what completed, so an interrupted batch can be resumed
+    let fingerprints = config.fingerprints();
+    let journal = if flags.resume {
+        Journal::resume(&flags.journal)
+    } else {
+        Journal::create(&flags.journal)
+    };
+    let mut journal = match journal {
+        Ok(journal) => Some(journal),
+        Err(e) if flags.resume => {
+            eprintln!(
+                "failed to open the journal:'{}' error:'{:?}'",
+                &flags.journal, e
+            );
+            ::std::process::exit(1);
+        }
+        Err(e) => {
+            // the batch can still run, it just can't be resumed
+            eprintln!(
+                "trollrun: warning, failed to open the journal:'{}' error:'{:?}', this batch can't be resumed",
+                &flags.journal, e
+            );
+            None
+        }
+    };
+    let completed: BTreeMap<String, TrollRecordable> = match journal {
+        Option::Some(ref journal) if flags.resume => journal.completed(&fingerprints),
+        _ => BTreeMap::new(),
+    };
+    let runs: Vec<TrollRun> = runs
+        .into_iter()
+        .filter(|run| !completed.contains_key(&run.name))
+        .collect();
+    if flags.resume {
+        eprintln!(
+            "trollrun: resuming, {} of {} runs were already completed",
+            completed.len(),
+            fingerprints.len()
+        );
+    }
+    // resumed runs go through the same checks as the ones executed now
+    let mut invalid = false;
+    let mut data: BTreeMap<String, TrollRecordable> = BTreeMap::new();
+    for (name, item) in completed {
+        let output = TrollOutput::resumed(item, flags.strict);
+        invalid |= output.result.is_invalid();
+        if let Option::Some(item) = output.into_result() {
+            data.insert(name, item);
+        }
+    }
+    let mut stream = open_stream(&flags.stream);
+    let batch = run_program(runs, !flags.quiet, &mut |item| {
+        record(&mut stream, item);
+        if let Option::Some(ref mut journal) = journal {
+            if let Err(e) = journal.record(item, fingerprints[&item.name]) {
+                eprintln!("failed to journal run:'{}' error:'{:?}'", item.name, e);
+            }
+        }
+    });
+    if let Option::Some(ref path) = flags.timings {
+        if let Err(e) = write_timings(path, &batch.reports) {
+            eprintln!("failed to write timings:'{}' error:'{:?}'", path, e);
+        }
+    }
+    invalid |= batch.reports.iter().any(|report| report.is_invalid());
+    // runs are built in name order, keep resumed runs in their place
+    data.extend(batch.data.into_iter().map(|item| (item.name.clone(), item)));
+    let data: Vec<TrollRecordable> = data.into_values().collect();
+    if batch.interrupted {
+        // the real outputs are left alone, what completed is clearly marked as partial
+        write_partial(&config.outputs(), &data, flags.behavior, &config.text);
+        eprintln!(
+            "trollrun: interrupted, the {} completed runs were written with a `.partial` suffix, use --resume to finish the batch",
+            data.len()
+        );
+        ::std::process::exit(exit_code());
+    }
+    // fan the results out to each output
+    let outputs: Vec<OutputWriter> = config
+        .outputs()
+        .iter()
+        .map(|cfg| cfg.build_config())
+        .collect();
+    if !write_all(outputs, &data, flags.behavior, &config.text) {
+        ::std::process::exit(1);
+    }
+    if invalid {
+        ::std::process::exit(2);
+    }
 }
@@ -1,9 +1,9 @@
-use std::fs::OpenOptions;
-use std::io::{stdout, Write};
+use std::io::Write;
 
 use super::super::super::csv::{QuoteStyle, Terminator, Writer, WriterBuilder};
 use super::super::super::serde::Deserialize;
 
+use super::super::open_output;
 use super::ser::CSVWriter;
 
 #[derive(Clone, Debug, Deserialize, Default)]
@@ -16,46 +16,42 @@ pub struct CSVConfig {
     pub flush_to_zero: Option<f64>,
     #[serde(default)]
     pub eol: Option<EOLSpecification>,
+    #[serde(default)]
+    pub layout: CSVLayout,
+    pub value_header: Option<String>,
+    #[serde(default)]
+    pub order: Vec<String>,
 }
 impl CSVConfig {
     /// handles deserializing the input configuration junk
-    pub fn build_config(config: &Option<Self>) -> CSVWriter {
-        let normal = Self::default();
-        let config = match config {
-            &Option::None => &normal,
-            &Option::Some(ref arg) => arg,
-        };
-        let eol = get_eol_specification(&config.eol);
-
+    pub fn build_config(&self) -> CSVWriter {
         // determine where to write to
-        let output: Box<dyn Write> = match &config.path {
-            &Option::Some(ref path) => {
-                match OpenOptions::new()
-                    .read(false)
-                    .write(true)
-                    .create(true)
-                    .truncate(true)
-                    .open(path)
-                {
-                    Err(e) => panic!("could not open file:'{}' error:'{:?}'", path, e),
-                    Ok(file) => Box::new(file),
-                }
-            }
-            _ => Box::new(stdout()),
-        };
+        let output: Box<dyn Write> = open_output(&self.path);
+        CSVWriter::new(output, self)
+    }
 
-        CSVWriter::new(
-            output,
-            &config.seperator,
-            &config.quote,
-            &config.precision,
-            &config.zero_pad,
-            &config.flush_to_zero,
-            eol,
-        )
+    /// the line terminator to write
+    pub fn terminator(&self) -> Terminator {
+        get_eol_specification(&self.eol)
     }
 }
 
+/*
+ * Layout Handling
+ *
+ */
+
+/// CSVLayout determines which way the table faces
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CSVLayout {
+    /// each run is a column, each value is a row
+    #[default]
+    Columns,
+    /// each run is a row, each value is a column
+    Transposed,
+}
+
 /*
  * EOL Handling
  *
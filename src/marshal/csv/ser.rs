@@ -4,53 +4,42 @@ use super::super::super::csv::{QuoteStyle, Result, Terminator, Writer, WriterBui
 use super::super::super::serde::Deserialize;
 
 use super::super::super::cli::StatBehavior;
+use super::super::super::exec::parser::Outcome;
 use super::super::super::exec::runs::TrollRecordable;
+use super::super::prep::{flush_to_zero, order_columns, preprocess, value_header};
+use super::config::{CSVConfig, CSVLayout};
 
 /// CSVWriter handles the semantics of writing data to the underlying file
 pub struct CSVWriter {
     zero_pad: usize,
     precision: usize,
     flush_to_zero: f64,
+    layout: CSVLayout,
+    value_header: String,
+    order: Vec<String>,
     writer: Writer<Box<dyn io::Write>>,
 }
 impl CSVWriter {
-    /// Create a new writer from the configuration arguments
-    pub fn new(
-        writer: Box<dyn io::Write>,
-        seperator: &Option<char>,
-        quote: &Option<char>,
-        precision: &Option<usize>,
-        zero_pad: &Option<usize>,
-        flush_to_zero: &Option<f64>,
-        eol: Terminator,
-    ) -> Self {
+    /// Create a new writer from the configuration
+    pub fn new(writer: Box<dyn io::Write>, config: &CSVConfig) -> Self {
         let mut w = WriterBuilder::new();
         w.has_headers(false);
-        match seperator {
-            &Option::Some(ref c) if c.is_ascii() => w.delimiter(*c as u32 as u8),
+        match config.seperator {
+            Option::Some(c) if c.is_ascii() => w.delimiter(c as u32 as u8),
             _ => w.delimiter(44),
         };
-        match quote {
-            &Option::Some(ref c) if c.is_ascii() => w.quote(*c as u32 as u8),
+        match config.quote {
+            Option::Some(c) if c.is_ascii() => w.quote(c as u32 as u8),
             _ => w.quote(34),
         };
-        w.terminator(eol);
-        let flush_to_zero = match flush_to_zero {
-            &Option::Some(ref f) if *f > 0.0 => *f,
-            _ => 0.0,
-        };
-        let precision = match precision {
-            &Option::Some(ref p) => *p,
-            _ => 0,
-        };
-        let zero_pad = match zero_pad {
-            &Option::Some(ref z) => *z,
-            _ => 0,
-        };
+        w.terminator(config.terminator());
         Self {
-            flush_to_zero,
-            zero_pad,
-            precision,
+            flush_to_zero: flush_to_zero(&config.flush_to_zero),
+            zero_pad: config.zero_pad.unwrap_or(0),
+            precision: config.precision.unwrap_or(0),
+            layout: config.layout,
+            value_header: value_header(&config.value_header),
+            order: config.order.clone(),
             writer: w.from_writer(writer),
         }
     }
@@ -63,29 +52,70 @@ impl CSVWriter {
     ) -> Result<()> {
         let mut data = data;
         // remove cut off data, and padd to equal length
-        let longest = preprocess(&self.flush_to_zero, &mut data);
+        let outcomes = preprocess(&self.flush_to_zero, &mut data);
+        // put the columns into their presentation order
+        order_columns(&self.order, &mut data);
         // split our data into 2 different components (names & stats)
-        let (names, stats) = break_it_up(data, longest, behavior);
+        let (names, stats) = break_it_up(data, outcomes.len(), behavior, &self.value_header);
 
+        match self.layout {
+            CSVLayout::Columns => self.write_columns(&names, &outcomes, &stats),
+            CSVLayout::Transposed => self.write_transposed(&names, &outcomes, &stats),
+        }
+    }
+
+    /// writes each run as a column, the first column holds the values
+    fn write_columns(
+        &mut self,
+        names: &[String],
+        outcomes: &[Outcome],
+        stats: &[Vec<f64>],
+    ) -> Result<()> {
         // build a buffer to hold our serialized data
-        let mut output_buffer: Vec<f64> = (0..names.len()).map(|_| 0.0).collect();
+        let mut output_buffer: Vec<f64> = (0..stats.len()).map(|_| 0.0).collect();
 
         // write the names of the columns
-        self.write_headers(names.as_slice())?;
+        self.write_headers(names)?;
 
         // loop over our stats (row by row)
-        for row in 0..longest {
+        for (row, outcome) in outcomes.iter().enumerate() {
             // loop over each collect (column by column)
-            for column in 0..names.len() {
+            for column in 0..stats.len() {
                 // row values into our temporary buffer
                 output_buffer[column] = stats[column][row];
             }
             // serialize the output
+            self.write_outcome(outcome)?;
             self.write_data(output_buffer.as_slice())?;
         }
         Ok(())
     }
 
+    /// writes each run as a row, the first row holds the values
+    fn write_transposed(
+        &mut self,
+        names: &[String],
+        outcomes: &[Outcome],
+        stats: &[Vec<f64>],
+    ) -> Result<()> {
+        self.writer.write_field(&names[0])?;
+        for outcome in outcomes {
+            self.write_outcome(outcome)?;
+        }
+        self.writer.write_record(Option::<&[u8]>::None)?;
+        for (name, row) in names[1..].iter().zip(stats.iter()) {
+            self.writer.write_field(name)?;
+            self.write_data(row.as_slice())?;
+        }
+        Ok(())
+    }
+
+    /// numbers are written as plain integers (they're values, not probabilities
+    /// so they aren't flushed or rounded), other outcomes are written as labels
+    fn write_outcome(&mut self, outcome: &Outcome) -> Result<()> {
+        self.writer.write_field(outcome.to_string())
+    }
+
     /// writer_headers starts the CSV serialization process by creating the header structure
     fn write_headers(&mut self, headers: &[String]) -> Result<()> {
         for header in headers {
@@ -116,59 +146,21 @@ impl CSVWriter {
     }
 }
 
-/*
- * Pre-Processing Helpers to manage data alignment
- *
- */
-
-/// remove values which config says are unimportant
-fn drop_trivial(flush_to_zero: &f64, data: &mut Vec<TrollRecordable>) {
-    if *flush_to_zero <= 0.0 {
-        return;
-    }
-    for vector in data.iter_mut() {
-        vector.result.trim_less_than(flush_to_zero);
-    }
-}
-
-/// find the longest dataset
-fn find_data_maximum(data: &Vec<TrollRecordable>) -> usize {
-    data.iter()
-        .map(|vector| vector.result.last_value())
-        .fold(0usize, |max, curr| if curr > max { curr } else { max })
-}
-
-/// insert junk 0's to ensure each dataset is of equal length
-fn pad_to_maximum(data: &mut Vec<TrollRecordable>, maximum: usize) {
-    for vector in data.iter_mut() {
-        vector.result.pad_to(maximum);
-    }
-}
-
-fn preprocess(cutoff: &f64, data: &mut Vec<TrollRecordable>) -> usize {
-    drop_trivial(cutoff, data);
-    let longest = find_data_maximum(data);
-    pad_to_maximum(data, longest);
-    longest
-}
-
 /*
  * Pre-Processing Helpers which handle formatting
  *
  */
 
+/// the names are headed by the value column, which has no stats
 fn break_it_up(
     data: Vec<TrollRecordable>,
-    maximum: usize,
+    max: usize,
     behavior: StatBehavior,
+    value_header: &str,
 ) -> (Vec<String>, Vec<Vec<f64>>) {
-    // actual max for this function
-    let max = maximum + 1;
-    let mut names: Vec<String> = vec!["Damage".into()];
-    let damages: Vec<f64> = (0..max).map(|x| x as f64).collect();
-    let mut values: Vec<Vec<f64>> = vec![damages];
-    for item in data {
-        let (name, stats) = item.split(behavior);
+    let mut names: Vec<String> = vec![value_header.into()];
+    let mut values: Vec<Vec<f64>> = Vec::new();
+    for (name, stats) in data.into_iter().flat_map(|item| item.split(behavior)) {
         assert_eq!(stats.len(), max, "expected stats to have same length as everything else. length:'{}' expected:'{}' for value:'{}'", stats.len(), max, &name);
         names.push(name);
         values.push(stats);
@@ -177,7 +169,7 @@ fn break_it_up(
     // debug assertions
     assert_eq!(
         names.len(),
-        values.len(),
+        values.len() + 1,
         "expected # of names to be the same as # of columns. names:'{}' columns:'{}'",
         names.len(),
         values.len()
@@ -197,3 +189,151 @@ fn format_float(x: &f64, zero_pad: &usize, prec: &usize, flush_to_zero: &f64) ->
     }
     format!("{:0z$.p$}", x, z = *zero_pad, p = *prec)
 }
+
+#[test]
+fn test_negative_outcomes() {
+    use super::super::super::exec::data::TrollData;
+    use super::super::super::exec::parser::TrollLine;
+    use super::super::super::exec::runs::ProgramInfo;
+    use super::config::EOLSpecification;
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file, File};
+
+    let path = temp_dir().join(format!("trollrun-{}-negative.csv", ::std::process::id()));
+    let dut = "  ~2: 25.0 100.0\n  0: 50.0 75.0\n  1: 25.0 25.0\n";
+    let data = || {
+        vec![TrollRecordable {
+            name: "n".to_string(),
+            label: None,
+            program: ProgramInfo::default(),
+            result: dut
+                .lines()
+                .filter_map(TrollLine::new)
+                .collect::<TrollData>(),
+        }]
+    };
+    let write = |layout: CSVLayout| {
+        let config = CSVConfig {
+            precision: Some(1),
+            eol: Some(EOLSpecification {
+                lf: Some(true),
+                ..Default::default()
+            }),
+            layout,
+            ..Default::default()
+        };
+        let mut writer = CSVWriter::new(Box::new(File::create(&path).unwrap()), &config);
+        writer
+            .serialize_output(data(), StatBehavior::RawStats)
+            .unwrap();
+        writer.flush().unwrap();
+        read_to_string(&path).unwrap()
+    };
+
+    assert_eq!(
+        write(CSVLayout::Columns),
+        "Damage,n\n-2,25.0\n-1,0.0\n0,50.0\n1,25.0\n"
+    );
+    assert_eq!(
+        write(CSVLayout::Transposed),
+        "Damage,-2,-1,0,1\nn,25.0,0.0,50.0,25.0\n"
+    );
+    remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_transposed() {
+    use super::super::super::exec::data::TrollData;
+    use super::super::super::exec::parser::TrollLine;
+    use super::super::super::exec::runs::ProgramInfo;
+    use super::config::EOLSpecification;
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file, File};
+
+    let path = temp_dir().join(format!("trollrun-{}-transposed.csv", ::std::process::id()));
+    let lines = |lines: &[&str]| -> TrollData {
+        lines
+            .iter()
+            .map(|line| TrollLine::new(line).unwrap())
+            .collect()
+    };
+    let data = vec![
+        TrollRecordable {
+            name: "a".to_string(),
+            label: None,
+            program: ProgramInfo::default(),
+            result: lines(&["  1: 50.0 100.0", "  2: 50.0 50.0"]),
+        },
+        TrollRecordable {
+            name: "b".to_string(),
+            label: Some("second".to_string()),
+            program: ProgramInfo::default(),
+            result: lines(&["  2: 25.0 100.0", "  3: 75.0 75.0"]),
+        },
+    ];
+    let config = CSVConfig {
+        precision: Some(1),
+        eol: Some(EOLSpecification {
+            lf: Some(true),
+            ..Default::default()
+        }),
+        layout: CSVLayout::Transposed,
+        ..Default::default()
+    };
+
+    let mut writer = CSVWriter::new(Box::new(File::create(&path).unwrap()), &config);
+    writer
+        .serialize_output(data, StatBehavior::RawStats)
+        .unwrap();
+    writer.flush().unwrap();
+    assert_eq!(
+        read_to_string(&path).unwrap(),
+        "Damage,0,1,2,3\na,0.0,50.0,50.0,0.0\nsecond,0.0,0.0,25.0,75.0\n"
+    );
+    remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_value_header_and_order() {
+    use super::super::super::exec::data::TrollData;
+    use super::super::super::exec::parser::TrollLine;
+    use super::super::super::exec::runs::ProgramInfo;
+    use super::config::EOLSpecification;
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file, File};
+
+    let path = temp_dir().join(format!("trollrun-{}-order.csv", ::std::process::id()));
+    let run = |name: &str| TrollRecordable {
+        name: name.to_string(),
+        label: None,
+        program: ProgramInfo::default(),
+        result: ["  0: 50.0 100.0", "  1: 50.0 50.0"]
+            .iter()
+            .map(|line| TrollLine::new(line).unwrap())
+            .collect::<TrollData>(),
+    };
+    // runs arrive in alphabetical order
+    let data = vec![run("a"), run("b"), run("c"), run("d")];
+    let config = CSVConfig {
+        precision: Some(1),
+        eol: Some(EOLSpecification {
+            lf: Some(true),
+            ..Default::default()
+        }),
+        value_header: Some("Hits".to_string()),
+        order: vec!["d".to_string(), "missing".to_string(), "b".to_string()],
+        ..Default::default()
+    };
+
+    let mut writer = CSVWriter::new(Box::new(File::create(&path).unwrap()), &config);
+    writer
+        .serialize_output(data, StatBehavior::RawStats)
+        .unwrap();
+    writer.flush().unwrap();
+    // listed runs come first, the unknown name is skipped, the rest stay alphabetical
+    assert_eq!(
+        read_to_string(&path).unwrap(),
+        "Hits,d,b,a,c\n0,50.0,50.0,50.0,50.0\n1,50.0,50.0,50.0,50.0\n"
+    );
+    remove_file(&path).unwrap();
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read_to_string, remove_file, write, File, OpenOptions};
+use std::io::{Result, Write};
+use std::path::{Path, PathBuf};
+
+use super::super::serde::{Deserialize, Serialize};
+use super::super::serde_json::{from_str, to_string, to_vec};
+
+use super::super::exec::hash::fnv1a;
+use super::super::exec::runs::TrollRecordable;
+
+/// Journal records which runs of a batch completed, so an interrupted
+/// batch can be resumed.
+///
+/// It lives in its own directory (`<config>.journal/` unless `--journal` says
+/// otherwise). `index.jsonl` has a line for every completed run, naming the
+/// file its data was written to.
+pub struct Journal {
+    dir: PathBuf,
+    index: File,
+}
+
+/// Entry is a line of the journal's index
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    name: String,
+    fingerprint: String,
+    data: String,
+}
+
+impl Journal {
+    /// starts a new journal, forgetting what an old one recorded
+    pub fn create(dir: &str) -> Result<Journal> {
+        let dir = PathBuf::from(dir);
+        for entry in entries(&dir) {
+            let _ = remove_file(dir.join(&entry.data));
+        }
+        create_dir_all(&dir)?;
+        let index = File::create(dir.join(INDEX))?;
+        Ok(Journal { dir, index })
+    }
+
+    /// opens the journal, keeping what it recorded
+    pub fn resume(dir: &str) -> Result<Journal> {
+        let dir = PathBuf::from(dir);
+        create_dir_all(&dir)?;
+        let index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(INDEX))?;
+        Ok(Journal { dir, index })
+    }
+
+    /// the completed runs which are still up to date.
+    ///
+    /// Runs whose fingerprint changed since they were recorded (or whose data
+    /// can't be read back) are left out, so they're executed again.
+    pub fn completed(
+        &self,
+        fingerprints: &BTreeMap<String, u64>,
+    ) -> BTreeMap<String, TrollRecordable> {
+        let mut completed = BTreeMap::new();
+        // later entries replace earlier ones for the same run
+        let mut latest: BTreeMap<String, Entry> = BTreeMap::new();
+        for entry in entries(&self.dir) {
+            latest.insert(entry.name.clone(), entry);
+        }
+        for (name, entry) in latest {
+            match fingerprints.get(&name) {
+                Option::Some(fingerprint)
+                    if format_fingerprint(*fingerprint) == entry.fingerprint => {}
+                _ => continue,
+            };
+            let item = read_to_string(self.dir.join(&entry.data))
+                .ok()
+                .and_then(|data| from_str::<TrollRecordable>(&data).ok())
+                .filter(|item| item.name == name);
+            if let Option::Some(item) = item {
+                completed.insert(name, item);
+            }
+        }
+        completed
+    }
+
+    /// records a completed run.
+    ///
+    /// The data is written before the index line, so the index never names
+    /// a file which doesn't exist.
+    pub fn record(&mut self, item: &TrollRecordable, fingerprint: u64) -> Result<()> {
+        let fingerprint = format_fingerprint(fingerprint);
+        // the fingerprint doesn't cover the name, & sanitizing can make two names
+        // the same, so the hash of the name keeps their files apart
+        let data = format!(
+            "{}-{:016x}-{}.json",
+            sanitize(&item.name),
+            fnv1a(item.name.as_bytes()),
+            &fingerprint
+        );
+        write(self.dir.join(&data), to_vec(item)?)?;
+        let entry = Entry {
+            name: item.name.clone(),
+            fingerprint,
+            data,
+        };
+        let mut line = to_string(&entry)?;
+        line.push('\n');
+        self.index.write_all(line.as_bytes())
+    }
+}
+
+const INDEX: &str = "index.jsonl";
+
+/// reads the index, a line cut short by a crash is skipped
+fn entries(dir: &Path) -> Vec<Entry> {
+    match read_to_string(dir.join(INDEX)) {
+        Err(_) => Vec::new(),
+        Ok(index) => index
+            .lines()
+            .filter_map(|line| from_str::<Entry>(line).ok())
+            .collect(),
+    }
+}
+
+fn format_fingerprint(fingerprint: u64) -> String {
+    format!("{:016x}", fingerprint)
+}
+
+/// run names become file names
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_journal_resume() {
+    use super::super::exec::data::TrollData;
+    use super::super::exec::runs::ProgramInfo;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let dir = temp_dir().join(format!("trollrun-{}-journal", ::std::process::id()));
+    let dir = dir.to_string_lossy().into_owned();
+    let item = |name: &str| TrollRecordable {
+        name: name.to_string(),
+        label: Some(format!("{} label", name)),
+        program: ProgramInfo::default(),
+        result: TrollData::from_samples(&[1, 2, 2]),
+    };
+    let mut fingerprints = BTreeMap::new();
+    fingerprints.insert("done".to_string(), 1);
+    fingerprints.insert("changed".to_string(), 2);
+    fingerprints.insert("todo".to_string(), 3);
+
+    let mut journal = Journal::create(&dir).unwrap();
+    journal.record(&item("done"), 1).unwrap();
+    journal.record(&item("changed"), 20).unwrap();
+
+    let completed = Journal::resume(&dir).unwrap().completed(&fingerprints);
+    assert_eq!(completed.keys().collect::<Vec<_>>(), vec!["done"]);
+
+    assert_eq!(completed["done"].header(), "done label");
+    assert_eq!(completed["done"].result.samples(), Some(3));
+
+    // names which only differ in punctuation, with the same entry, don't share a file
+    let mut journal = Journal::resume(&dir).unwrap();
+    journal.record(&item("a b"), 4).unwrap();
+    journal.record(&item("a_b"), 4).unwrap();
+    fingerprints.insert("a b".to_string(), 4);
+    fingerprints.insert("a_b".to_string(), 4);
+    let completed = Journal::resume(&dir).unwrap().completed(&fingerprints);
+    assert_eq!(completed["a b"].name, "a b");
+    assert_eq!(completed["a_b"].name, "a_b");
+
+    // a new batch forgets everything
+    let completed = Journal::create(&dir).unwrap().completed(&fingerprints);
+    assert!(completed.is_empty());
+    remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,19 @@
+use std::io::Write;
+
+use super::super::super::serde::Deserialize;
+
+use super::super::open_output;
+use super::ser::JSONWriter;
+
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct JSONConfig {
+    pub path: Option<String>,
+    pub pretty: Option<bool>,
+}
+impl JSONConfig {
+    /// handles deserializing the input configuration junk
+    pub fn build_config(&self) -> JSONWriter {
+        let output: Box<dyn Write> = open_output(&self.path);
+        JSONWriter::new(output, self.pretty.unwrap_or(false))
+    }
+}
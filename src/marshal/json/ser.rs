@@ -0,0 +1,85 @@
+use std::io::{self, Write};
+
+use super::super::super::serde::Serialize;
+use super::super::super::serde_json::{to_writer, to_writer_pretty, Error, Result};
+
+use super::super::super::exec::runs::TrollRecordable;
+
+/// JSONWriter writes every run (with its program & full distribution) as one document
+pub struct JSONWriter {
+    pretty: bool,
+    writer: Box<dyn io::Write>,
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    runs: &'a [TrollRecordable],
+}
+
+impl JSONWriter {
+    /// Create a new writer from the configuration arguments
+    pub fn new(writer: Box<dyn io::Write>, pretty: bool) -> Self {
+        Self { pretty, writer }
+    }
+
+    /// writes the document
+    pub fn serialize_output(&mut self, data: &[TrollRecordable]) -> Result<()> {
+        let doc = Document { runs: data };
+        if self.pretty {
+            to_writer_pretty(&mut self.writer, &doc)?;
+        } else {
+            to_writer(&mut self.writer, &doc)?;
+        }
+        writeln!(self.writer).map_err(Error::io)
+    }
+
+    /// cleans up the object flushing the underlying IO
+    pub fn flush(self) -> io::Result<()> {
+        let mut s = self;
+        s.writer.flush()
+    }
+}
+
+#[test]
+fn test_json_round_trip() {
+    use super::super::super::exec::data::TrollData;
+    use super::super::super::exec::runs::ProgramInfo;
+    use super::super::super::serde_json::{from_str, from_value, Value};
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file, File};
+
+    let path = temp_dir().join(format!("trollrun-{}-round-trip.json", ::std::process::id()));
+    let mut program = ProgramInfo {
+        path: "dice.t".to_string(),
+        hash: Some("cbf29ce484222325".to_string()),
+        ..Default::default()
+    };
+    program.args.insert("rr".to_string(), 1);
+    let data = vec![TrollRecordable {
+        name: "dice".to_string(),
+        label: Some("Dice \"d6\"".to_string()),
+        program,
+        result: TrollData::from_samples(&[1, 2, 2, 6]),
+    }];
+
+    let mut writer = JSONWriter::new(Box::new(File::create(&path).unwrap()), true);
+    writer.serialize_output(&data).unwrap();
+    writer.flush().unwrap();
+    let mut doc: Value = from_str(&read_to_string(&path).unwrap()).unwrap();
+    let runs: Vec<TrollRecordable> = from_value(doc["runs"].take()).unwrap();
+
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].name, data[0].name);
+    assert_eq!(runs[0].label, data[0].label);
+    assert_eq!(runs[0].program.path, "dice.t");
+    assert_eq!(runs[0].program.args["rr"], 1);
+    assert_eq!(runs[0].program.hash, data[0].program.hash);
+    assert_eq!(runs[0].result.samples(), Some(4));
+    assert_eq!(runs[0].result.len(), data[0].result.len());
+    for index in 0..data[0].result.len() {
+        assert_eq!(runs[0].result[index].value, data[0].result[index].value);
+        assert_eq!(runs[0].result[index].prob, data[0].result[index].prob);
+        assert_eq!(runs[0].result[index].accum, data[0].result[index].accum);
+    }
+    remove_file(&path).unwrap();
+}
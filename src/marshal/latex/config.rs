@@ -0,0 +1,27 @@
+use std::io::Write;
+
+use super::super::super::serde::Deserialize;
+
+use super::super::open_output;
+use super::ser::LaTeXWriter;
+
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct LaTeXConfig {
+    pub path: Option<String>,
+    pub precision: Option<usize>,
+    pub flush_to_zero: Option<f64>,
+    pub max_columns: Option<usize>,
+    pub caption: Option<String>,
+    pub value_header: Option<String>,
+    #[serde(default)]
+    pub order: Vec<String>,
+}
+impl LaTeXConfig {
+    /// handles deserializing the input configuration junk
+    pub fn build_config(&self) -> LaTeXWriter {
+        // determine where to write to
+        let output: Box<dyn Write> = open_output(&self.path);
+
+        LaTeXWriter::new(output, self)
+    }
+}
@@ -0,0 +1,220 @@
+use std::io::{self, Result, Write};
+
+use super::super::super::cli::StatBehavior;
+use super::super::super::exec::parser::Outcome;
+use super::super::super::exec::runs::TrollRecordable;
+use super::super::prep::{flush_to_zero, order_columns, preprocess, value_header};
+use super::config::LaTeXConfig;
+
+/// LaTeXWriter renders the data as `booktabs` tables with `siunitx` columns
+pub struct LaTeXWriter {
+    precision: usize,
+    flush_to_zero: f64,
+    max_columns: usize,
+    caption: Option<String>,
+    value_header: String,
+    order: Vec<String>,
+    writer: Box<dyn io::Write>,
+}
+impl LaTeXWriter {
+    /// Create a new writer from the configuration
+    pub fn new(writer: Box<dyn io::Write>, config: &LaTeXConfig) -> Self {
+        // zero means "don't split"
+        let max_columns = match config.max_columns {
+            Option::Some(m) if m > 0 => m,
+            _ => usize::MAX,
+        };
+        Self {
+            precision: config.precision.unwrap_or(0),
+            flush_to_zero: flush_to_zero(&config.flush_to_zero),
+            max_columns,
+            caption: config.caption.clone(),
+            value_header: value_header(&config.value_header),
+            order: config.order.clone(),
+            writer,
+        }
+    }
+
+    /// handles splitting & mangling the data before writing it
+    pub fn serialize_output(
+        &mut self,
+        data: Vec<TrollRecordable>,
+        behavior: StatBehavior,
+    ) -> Result<()> {
+        let mut data = data;
+        let outcomes = preprocess(&self.flush_to_zero, &mut data);
+        order_columns(&self.order, &mut data);
+        // a sampled run spans several columns, they're kept in the same table
+        let runs: Vec<Vec<(String, Vec<f64>)>> =
+            data.into_iter().map(|item| item.split(behavior)).collect();
+
+        // an empty table still gets written so the document compiles
+        if runs.is_empty() {
+            return self.write_table(&[], &outcomes, 0);
+        }
+        for (part, chunk) in runs.chunks(self.max_columns).enumerate() {
+            self.write_table(&chunk.concat(), &outcomes, part)?;
+        }
+        Ok(())
+    }
+
+    /// writes a single table, `part` is non-zero for continuations of a split table
+    fn write_table(
+        &mut self,
+        columns: &[(String, Vec<f64>)],
+        outcomes: &[Outcome],
+        part: usize,
+    ) -> Result<()> {
+        writeln!(self.writer, "\\begin{{table}}")?;
+        writeln!(self.writer, "\\centering")?;
+        if let Option::Some(ref caption) = self.caption {
+            if part == 0 {
+                writeln!(self.writer, "\\caption{{{}}}", escape(caption))?;
+            } else {
+                writeln!(self.writer, "\\caption{{{} (continued)}}", escape(caption))?;
+            }
+        }
+        // percentages need at most 3 integer digits (100)
+        let format = format!("S[table-format=3.{}]", self.precision);
+        let spec: Vec<String> = (0..columns.len()).map(|_| format.clone()).collect();
+        // outcomes which aren't numbers are labels, in a plain left aligned column
+        let value_spec = match outcomes
+            .iter()
+            .map(|o| o.integer())
+            .collect::<Option<Vec<i64>>>()
+        {
+            Option::Some(values) => {
+                let longest = values.iter().map(|v| v.unsigned_abs()).max().unwrap_or(0);
+                // siunitx needs room for the sign of negative values
+                let sign = if values.iter().any(|v| *v < 0) {
+                    "-"
+                } else {
+                    ""
+                };
+                format!("S[table-format={}{}.0]", sign, longest.to_string().len())
+            }
+            Option::None => "l".to_string(),
+        };
+        writeln!(
+            self.writer,
+            "\\begin{{tabular}}{{{} {}}}",
+            value_spec,
+            spec.join(" ")
+        )?;
+        writeln!(self.writer, "\\toprule")?;
+
+        // headers are braced so siunitx doesn't try to parse them
+        let mut headers = vec![format!("{{{}}}", escape(&self.value_header))];
+        for (name, _) in columns {
+            headers.push(format!("{{{}}}", escape(name)));
+        }
+        writeln!(self.writer, "{} \\\\", headers.join(" & "))?;
+        writeln!(self.writer, "\\midrule")?;
+
+        for (row, outcome) in outcomes.iter().enumerate() {
+            let mut fields = vec![escape(&outcome.to_string())];
+            for (_, stats) in columns {
+                fields.push(self.format_number(stats[row]));
+            }
+            writeln!(self.writer, "{} \\\\", fields.join(" & "))?;
+        }
+        writeln!(self.writer, "\\bottomrule")?;
+        writeln!(self.writer, "\\end{{tabular}}")?;
+        writeln!(self.writer, "\\end{{table}}")?;
+        writeln!(self.writer)
+    }
+
+    /// plain fixed point decimals are what siunitx parses best
+    fn format_number(&self, x: f64) -> String {
+        let x = if x <= self.flush_to_zero { 0.0 } else { x };
+        format!("{:.p$}", x, p = self.precision)
+    }
+
+    /// cleans up the object flushing the underlying IO
+    pub fn flush(self) -> Result<()> {
+        let mut s = self;
+        s.writer.flush()
+    }
+}
+
+/// escape characters which are special to LaTeX
+fn escape(arg: &str) -> String {
+    let mut output = String::with_capacity(arg.len());
+    for c in arg.chars() {
+        match c {
+            '\\' => output.push_str("\\textbackslash{}"),
+            '~' => output.push_str("\\textasciitilde{}"),
+            '^' => output.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                output.push('\\');
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(escape("Heavy Bolter (BS4)"), "Heavy Bolter (BS4)");
+    assert_eq!(escape("rr_1 & 50%"), "rr\\_1 \\& 50\\%");
+    assert_eq!(escape("{a}#$"), "\\{a\\}\\#\\$");
+    assert_eq!(
+        escape("a\\b~c^d"),
+        "a\\textbackslash{}b\\textasciitilde{}c\\textasciicircum{}d"
+    );
+}
+
+#[test]
+fn test_split_by_run() {
+    use super::super::super::exec::data::TrollData;
+    use super::super::super::exec::parser::TrollLine;
+    use super::super::super::exec::runs::ProgramInfo;
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file, File};
+
+    let path = temp_dir().join(format!("trollrun-{}-split.tex", ::std::process::id()));
+    let data = vec![
+        TrollRecordable {
+            name: "exact".to_string(),
+            label: None,
+            program: ProgramInfo::default(),
+            result: ["  1: 50.0 100.0", "  2: 50.0 50.0"]
+                .iter()
+                .map(|line| TrollLine::new(line).unwrap())
+                .collect::<TrollData>(),
+        },
+        TrollRecordable {
+            name: "sampled".to_string(),
+            label: None,
+            program: ProgramInfo::default(),
+            result: TrollData::from_samples(&[1, 2, 2]),
+        },
+    ];
+
+    let config = LaTeXConfig {
+        precision: Some(1),
+        max_columns: Some(1),
+        ..Default::default()
+    };
+    let mut writer = LaTeXWriter::new(Box::new(File::create(&path).unwrap()), &config);
+    writer
+        .serialize_output(data, StatBehavior::RawStats)
+        .unwrap();
+    writer.flush().unwrap();
+    let output = read_to_string(&path).unwrap();
+    let headers: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("{Damage}"))
+        .collect();
+    // the confidence interval stays with its run, even past max_columns
+    assert_eq!(
+        headers,
+        vec![
+            "{Damage} & {exact} \\\\",
+            "{Damage} & {sampled} & {sampled (95\\% low)} & {sampled (95\\% high)} \\\\",
+        ]
+    );
+    remove_file(&path).unwrap();
+}
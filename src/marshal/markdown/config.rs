@@ -0,0 +1,23 @@
+use std::io::Write;
+
+use super::super::super::serde::Deserialize;
+
+use super::super::open_output;
+use super::ser::MarkdownWriter;
+
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct MarkdownConfig {
+    pub path: Option<String>,
+    pub precision: Option<usize>,
+    pub flush_to_zero: Option<f64>,
+    pub value_header: Option<String>,
+    #[serde(default)]
+    pub order: Vec<String>,
+}
+impl MarkdownConfig {
+    /// handles deserializing the input configuration junk
+    pub fn build_config(&self) -> MarkdownWriter {
+        let output: Box<dyn Write> = open_output(&self.path);
+        MarkdownWriter::new(output, self)
+    }
+}
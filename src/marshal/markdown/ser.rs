@@ -0,0 +1,132 @@
+use std::io::{self, Result, Write};
+
+use super::super::super::cli::StatBehavior;
+use super::super::super::exec::runs::TrollRecordable;
+use super::super::prep::{flush_to_zero, order_columns, preprocess, value_header};
+use super::config::MarkdownConfig;
+
+/// MarkdownWriter renders the data as a pipe table
+pub struct MarkdownWriter {
+    precision: usize,
+    flush_to_zero: f64,
+    value_header: String,
+    order: Vec<String>,
+    writer: Box<dyn io::Write>,
+}
+impl MarkdownWriter {
+    /// Create a new writer from the configuration
+    pub fn new(writer: Box<dyn io::Write>, config: &MarkdownConfig) -> Self {
+        Self {
+            precision: config.precision.unwrap_or(0),
+            flush_to_zero: flush_to_zero(&config.flush_to_zero),
+            value_header: value_header(&config.value_header),
+            order: config.order.clone(),
+            writer,
+        }
+    }
+
+    /// handles splitting & mangling the data before writing it
+    pub fn serialize_output(
+        &mut self,
+        data: Vec<TrollRecordable>,
+        behavior: StatBehavior,
+    ) -> Result<()> {
+        let mut data = data;
+        let outcomes = preprocess(&self.flush_to_zero, &mut data);
+        order_columns(&self.order, &mut data);
+        let columns: Vec<(String, Vec<f64>)> = data
+            .into_iter()
+            .flat_map(|item| item.split(behavior))
+            .collect();
+
+        let mut headers = vec![escape(&self.value_header)];
+        // outcomes which aren't numbers are labels, these are left aligned
+        let mut alignment = if outcomes.iter().all(|outcome| outcome.integer().is_some()) {
+            vec!["---:".to_string()]
+        } else {
+            vec![":---".to_string()]
+        };
+        for (name, _) in columns.iter() {
+            headers.push(escape(name));
+            alignment.push("---:".to_string());
+        }
+        writeln!(self.writer, "| {} |", headers.join(" | "))?;
+        writeln!(self.writer, "|{}|", alignment.join("|"))?;
+
+        for (row, outcome) in outcomes.iter().enumerate() {
+            let mut fields = vec![escape(&outcome.to_string())];
+            for (_, stats) in columns.iter() {
+                let x = if stats[row] <= self.flush_to_zero {
+                    0.0
+                } else {
+                    stats[row]
+                };
+                fields.push(format!("{:.p$}", x, p = self.precision));
+            }
+            writeln!(self.writer, "| {} |", fields.join(" | "))?;
+        }
+        Ok(())
+    }
+
+    /// cleans up the object flushing the underlying IO
+    pub fn flush(self) -> Result<()> {
+        let mut s = self;
+        s.writer.flush()
+    }
+}
+
+/// pipes would end the cell early
+fn escape(arg: &str) -> String {
+    arg.replace('|', "\\|")
+}
+
+#[test]
+fn test_markdown_table() {
+    use super::super::super::exec::data::TrollData;
+    use super::super::super::exec::parser::TrollLine;
+    use super::super::super::exec::runs::ProgramInfo;
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file, File};
+
+    let path = temp_dir().join(format!("trollrun-{}-table.md", ::std::process::id()));
+    let lines = |lines: &[&str]| -> TrollData {
+        lines
+            .iter()
+            .map(|line| TrollLine::new(line).unwrap())
+            .collect()
+    };
+    let data = vec![
+        TrollRecordable {
+            name: "hit|miss".to_string(),
+            label: None,
+            program: ProgramInfo::default(),
+            result: lines(&[r#""hit|crit" : 25 100"#, r#""miss" : 75 75"#]),
+        },
+        TrollRecordable {
+            name: "second".to_string(),
+            label: Some("a | b".to_string()),
+            program: ProgramInfo::default(),
+            result: lines(&[r#""miss" : 100 100"#]),
+        },
+    ];
+
+    let config = MarkdownConfig {
+        precision: Some(1),
+        value_header: Some("Result".to_string()),
+        ..Default::default()
+    };
+    let mut writer = MarkdownWriter::new(Box::new(File::create(&path).unwrap()), &config);
+    writer
+        .serialize_output(data, StatBehavior::RawStats)
+        .unwrap();
+    writer.flush().unwrap();
+    assert_eq!(
+        read_to_string(&path).unwrap(),
+        r#"| Result | hit\|miss | a \| b |
+|:---|---:|---:|
+| hit\|crit | 25.0 | 0.0 |
+| miss | 75.0 | 100.0 |
+"#
+    );
+    remove_file(&path).unwrap();
+}
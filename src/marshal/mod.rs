@@ -1 +1,33 @@
+use std::fs::OpenOptions;
+use std::io::{stdout, Write};
+
 pub mod csv;
+pub mod journal;
+pub mod json;
+pub mod latex;
+pub mod markdown;
+pub mod output;
+pub mod prep;
+pub mod sqlite;
+pub mod stream;
+pub mod timings;
+pub mod xlsx;
+
+/// opens the file outputs are written to, or stdout when no path is given
+pub fn open_output(path: &Option<String>) -> Box<dyn Write> {
+    match path {
+        Option::Some(ref path) => {
+            match OpenOptions::new()
+                .read(false)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)
+            {
+                Err(e) => panic!("could not open file:'{}' error:'{:?}'", path, e),
+                Ok(file) => Box::new(file),
+            }
+        }
+        _ => Box::new(stdout()),
+    }
+}
@@ -0,0 +1,238 @@
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use super::super::serde::Deserialize;
+
+use super::super::cli::StatBehavior;
+use super::super::exec::runs::TrollRecordable;
+use super::csv::config::CSVConfig;
+use super::csv::ser::CSVWriter;
+use super::json::config::JSONConfig;
+use super::json::ser::JSONWriter;
+use super::latex::config::LaTeXConfig;
+use super::latex::ser::LaTeXWriter;
+use super::markdown::config::MarkdownConfig;
+use super::markdown::ser::MarkdownWriter;
+use super::sqlite::config::SQLiteConfig;
+use super::sqlite::ser::SQLiteWriter;
+use super::xlsx::config::XLSXConfig;
+use super::xlsx::ser::XLSXWriter;
+
+/// OutputConfig is a single `[[output]]` entry, `format` selects the writer
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum OutputConfig {
+    Csv(CSVConfig),
+    Json(JSONConfig),
+    Markdown(MarkdownConfig),
+    Sqlite(SQLiteConfig),
+    Xlsx(XLSXConfig),
+    Latex(LaTeXConfig),
+}
+impl OutputConfig {
+    /// the file the output is written to, `None` for stdout
+    fn path(&self) -> Option<&String> {
+        match self {
+            OutputConfig::Csv(cfg) => cfg.path.as_ref(),
+            OutputConfig::Json(cfg) => cfg.path.as_ref(),
+            OutputConfig::Markdown(cfg) => cfg.path.as_ref(),
+            OutputConfig::Sqlite(cfg) => Some(&cfg.path),
+            OutputConfig::Xlsx(cfg) => Some(&cfg.path),
+            OutputConfig::Latex(cfg) => cfg.path.as_ref(),
+        }
+    }
+
+    /// checks the output can be written, before troll is ran so bad paths are found early.
+    ///
+    /// Nothing is created or truncated, so an interrupted batch leaves old results alone.
+    pub fn check(&self) -> Result<(), String> {
+        let path = match self.path() {
+            Option::None => return Ok(()),
+            Option::Some(path) => Path::new(path),
+        };
+        if path.exists() {
+            return match OpenOptions::new().write(true).open(path) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(format!("cannot write:'{}' error:'{:?}'", path.display(), e)),
+            };
+        }
+        match path.parent() {
+            Option::Some(dir) if !dir.as_os_str().is_empty() && !dir.is_dir() => Err(format!(
+                "cannot write:'{}' the directory does not exist",
+                path.display()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// the same output, written next to the real one with a `.partial` suffix.
+    ///
+    /// `None` for outputs written to stdout, there is nothing to mark them with.
+    pub fn partial(&self) -> Option<OutputConfig> {
+        let partial = |path: &String| format!("{}.partial", path);
+        let mut output = self.clone();
+        match output {
+            OutputConfig::Csv(ref mut cfg) => cfg.path = Some(partial(cfg.path.as_ref()?)),
+            OutputConfig::Json(ref mut cfg) => cfg.path = Some(partial(cfg.path.as_ref()?)),
+            OutputConfig::Markdown(ref mut cfg) => cfg.path = Some(partial(cfg.path.as_ref()?)),
+            OutputConfig::Sqlite(ref mut cfg) => cfg.path = partial(&cfg.path),
+            OutputConfig::Xlsx(ref mut cfg) => cfg.path = partial(&cfg.path),
+            OutputConfig::Latex(ref mut cfg) => cfg.path = Some(partial(cfg.path.as_ref()?)),
+        };
+        Some(output)
+    }
+
+    /// opens the output
+    pub fn build_config(&self) -> OutputWriter {
+        match self {
+            OutputConfig::Csv(cfg) => OutputWriter::Csv(cfg.build_config()),
+            OutputConfig::Json(cfg) => OutputWriter::Json(cfg.build_config()),
+            OutputConfig::Markdown(cfg) => OutputWriter::Markdown(cfg.build_config()),
+            OutputConfig::Sqlite(cfg) => OutputWriter::Sqlite(cfg.build_config()),
+            OutputConfig::Xlsx(cfg) => OutputWriter::Xlsx(cfg.build_config()),
+            OutputConfig::Latex(cfg) => OutputWriter::Latex(cfg.build_config()),
+        }
+    }
+}
+
+/// OutputWriter is an opened output ready to receive results
+pub enum OutputWriter {
+    Csv(CSVWriter),
+    Json(JSONWriter),
+    Markdown(MarkdownWriter),
+    Sqlite(SQLiteWriter),
+    Xlsx(XLSXWriter),
+    Latex(LaTeXWriter),
+}
+impl OutputWriter {
+    /// writes all the data & flushes the output
+    pub fn write(
+        self,
+        data: Vec<TrollRecordable>,
+        behavior: StatBehavior,
+        config: &str,
+    ) -> Result<(), String> {
+        match self {
+            OutputWriter::Csv(mut w) => {
+                w.serialize_output(data, behavior)
+                    .map_err(|e| format!("{:?}", e))?;
+                w.flush().map_err(|e| format!("{:?}", e))
+            }
+            OutputWriter::Json(mut w) => {
+                w.serialize_output(&data).map_err(|e| format!("{:?}", e))?;
+                w.flush().map_err(|e| format!("{:?}", e))
+            }
+            OutputWriter::Markdown(mut w) => {
+                w.serialize_output(data, behavior)
+                    .map_err(|e| format!("{:?}", e))?;
+                w.flush().map_err(|e| format!("{:?}", e))
+            }
+            OutputWriter::Sqlite(mut w) => w
+                .serialize_output(&data, config)
+                .map_err(|e| format!("{:?}", e)),
+            OutputWriter::Xlsx(mut w) => w.serialize_output(data).map_err(|e| format!("{:?}", e)),
+            OutputWriter::Latex(mut w) => {
+                w.serialize_output(data, behavior)
+                    .map_err(|e| format!("{:?}", e))?;
+                w.flush().map_err(|e| format!("{:?}", e))
+            }
+        }
+    }
+
+    /// a short name used in error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OutputWriter::Csv(_) => "csv",
+            OutputWriter::Json(_) => "json",
+            OutputWriter::Markdown(_) => "markdown",
+            OutputWriter::Sqlite(_) => "sqlite",
+            OutputWriter::Xlsx(_) => "xlsx",
+            OutputWriter::Latex(_) => "latex",
+        }
+    }
+}
+
+/// writes the runs an interrupted batch completed, see `OutputConfig::partial`.
+///
+/// Outputs which go to stdout are left out, so what is printed is always a complete batch.
+pub fn write_partial(
+    outputs: &[OutputConfig],
+    data: &[TrollRecordable],
+    behavior: StatBehavior,
+    config: &str,
+) -> bool {
+    if outputs.iter().any(|cfg| cfg.path().is_none()) {
+        eprintln!("trollrun: partial results are not written to stdout");
+    }
+    let writers: Vec<OutputWriter> = outputs
+        .iter()
+        .filter_map(OutputConfig::partial)
+        .map(|cfg| cfg.build_config())
+        .collect();
+    write_all(writers, data, behavior, config)
+}
+
+/// writes the results to every output, reporting failures on stderr.
+///
+/// Returns `false` if any of the outputs could not be written.
+pub fn write_all(
+    outputs: Vec<OutputWriter>,
+    data: &[TrollRecordable],
+    behavior: StatBehavior,
+    config: &str,
+) -> bool {
+    let mut ok = true;
+    for output in outputs {
+        let kind = output.kind();
+        if let Err(e) = output.write(data.to_vec(), behavior, config) {
+            eprintln!("failed to write {} output error:'{}'", kind, e);
+            ok = false;
+        }
+    }
+    ok
+}
+
+#[test]
+fn test_write_all() {
+    use super::super::exec::data::TrollData;
+    use super::super::exec::runs::ProgramInfo;
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file};
+
+    let path = |ext: &str| {
+        let path = temp_dir().join(format!(
+            "trollrun-{}-write-all.{}",
+            ::std::process::id(),
+            ext
+        ));
+        path.to_string_lossy().into_owned()
+    };
+    let outputs = [
+        OutputConfig::Json(JSONConfig {
+            path: Some(path("json")),
+            pretty: None,
+        }),
+        OutputConfig::Markdown(MarkdownConfig {
+            path: Some(path("md")),
+            ..Default::default()
+        }),
+    ];
+    let data = vec![TrollRecordable {
+        name: "dice".to_string(),
+        label: None,
+        program: ProgramInfo::default(),
+        result: TrollData::from_samples(&[1, 2]),
+    }];
+
+    let writers = outputs.iter().map(|cfg| cfg.build_config()).collect();
+    assert!(write_all(writers, &data, StatBehavior::RawStats, ""));
+    // every output gets every run
+    assert!(read_to_string(path("json"))
+        .unwrap()
+        .starts_with(r#"{"runs":[{"name":"dice""#));
+    assert!(read_to_string(path("md"))
+        .unwrap()
+        .starts_with("| Damage | dice | dice (95% low) | dice (95% high) |\n"));
+    remove_file(path("json")).unwrap();
+    remove_file(path("md")).unwrap();
+}
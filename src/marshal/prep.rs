@@ -0,0 +1,119 @@
+use super::super::exec::parser::Outcome;
+use super::super::exec::runs::TrollRecordable;
+
+/*
+ * Configuration defaults shared by the writers
+ *
+ */
+
+/// probabilities at or below this are written as 0, nothing is flushed by default
+pub fn flush_to_zero(flush_to_zero: &Option<f64>) -> f64 {
+    match *flush_to_zero {
+        Option::Some(f) if f > 0.0 => f,
+        _ => 0.0,
+    }
+}
+
+/// the header of the column holding the values
+pub fn value_header(value_header: &Option<String>) -> String {
+    match *value_header {
+        Option::Some(ref header) => header.clone(),
+        _ => "Damage".into(),
+    }
+}
+
+/*
+ * Pre-Processing Helpers to manage data alignment
+ *
+ */
+
+/// remove values which config says are unimportant
+pub fn drop_trivial(flush_to_zero: &f64, data: &mut Vec<TrollRecordable>) {
+    if *flush_to_zero <= 0.0 {
+        return;
+    }
+    for vector in data.iter_mut() {
+        vector.result.trim_less_than(flush_to_zero);
+    }
+}
+
+/// find the longest dataset
+pub fn find_data_maximum(data: &Vec<TrollRecordable>) -> i64 {
+    data.iter()
+        .map(|vector| vector.result.last_value())
+        .fold(0i64, |max, curr| if curr > max { curr } else { max })
+}
+
+/// find the dataset which starts lowest, this is 0 unless there are negative values
+pub fn find_data_minimum(data: &[TrollRecordable]) -> i64 {
+    data.iter()
+        .map(|vector| vector.result.first_value())
+        .fold(0i64, |min, curr| if curr < min { curr } else { min })
+}
+
+/// insert junk 0's to ensure each dataset covers the same values
+pub fn pad_to_range(data: &mut Vec<TrollRecordable>, minimum: i64, maximum: i64) {
+    for vector in data.iter_mut() {
+        vector.result.pad_from(minimum);
+        vector.result.pad_to(maximum);
+    }
+}
+
+/// the outcomes each dataset is aligned to, these label the rows of the output.
+///
+/// When every outcome is a number this is every value from 0 (or the smallest
+/// negative value) up to the largest, otherwise
+/// it is every outcome of every run, in the order they are first seen.
+pub fn find_outcomes(data: &Vec<TrollRecordable>) -> Vec<Outcome> {
+    if data.iter().all(|vector| vector.result.is_numeric()) {
+        let lowest = find_data_minimum(data);
+        let longest = find_data_maximum(data);
+        return (lowest..(longest + 1)).map(Outcome::Integer).collect();
+    }
+    let mut outcomes: Vec<Outcome> = Vec::new();
+    for vector in data.iter() {
+        for outcome in vector.result.outcomes() {
+            if !outcomes.contains(&outcome) {
+                outcomes.push(outcome);
+            }
+        }
+    }
+    outcomes
+}
+
+/// ensure each dataset has a point for every outcome, in the same order
+pub fn align_outcomes(data: &mut Vec<TrollRecordable>, outcomes: &[Outcome]) {
+    if outcomes.iter().all(|outcome| outcome.integer().is_some()) {
+        let first = outcomes.first().and_then(Outcome::integer).unwrap_or(0);
+        let last = outcomes.last().and_then(Outcome::integer).unwrap_or(0);
+        pad_to_range(data, first, last);
+        return;
+    }
+    for vector in data.iter_mut() {
+        vector.result.align_to(outcomes);
+    }
+}
+
+/// returns the outcomes which label each row
+pub fn preprocess(cutoff: &f64, data: &mut Vec<TrollRecordable>) -> Vec<Outcome> {
+    drop_trivial(cutoff, data);
+    let outcomes = find_outcomes(data);
+    align_outcomes(data, &outcomes);
+    outcomes
+}
+
+/// sort the runs so the ones named in `order` come first (in that order),
+/// everything else keeps its alphabetical position afterwards
+pub fn order_columns(order: &[String], data: &mut [TrollRecordable]) {
+    for name in order {
+        if !data.iter().any(|item| &item.name == name) {
+            eprintln!("order names run:'{}' which does not exist", name);
+        }
+    }
+    data.sort_by_key(|item| {
+        order
+            .iter()
+            .position(|name| name == &item.name)
+            .unwrap_or(order.len())
+    });
+}
@@ -0,0 +1,18 @@
+use super::super::super::serde::Deserialize;
+
+use super::ser::SQLiteWriter;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SQLiteConfig {
+    pub path: String,
+    pub description: Option<String>,
+}
+impl SQLiteConfig {
+    /// opens (or creates) the database described by the configuration
+    pub fn build_config(&self) -> SQLiteWriter {
+        match SQLiteWriter::open(&self.path, &self.description) {
+            Err(e) => panic!("could not open database:'{}' error:'{:?}'", &self.path, e),
+            Ok(writer) => writer,
+        }
+    }
+}
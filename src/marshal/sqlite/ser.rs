@@ -0,0 +1,214 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::super::super::rusqlite::types::Value;
+use super::super::super::rusqlite::{params, Connection, Result, NO_PARAMS};
+
+use super::super::super::exec::parser::Outcome;
+use super::super::super::exec::runs::{ProgramInfo, TrollRecordable};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS batches (
+    id INTEGER PRIMARY KEY,
+    started_at INTEGER NOT NULL,
+    config TEXT NOT NULL,
+    description TEXT
+);
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    batch_id INTEGER NOT NULL REFERENCES batches(id),
+    name TEXT NOT NULL,
+    label TEXT,
+    program TEXT NOT NULL,
+    args TEXT NOT NULL,
+    program_hash TEXT,
+    samples INTEGER,
+    source TEXT,
+    bin TEXT,
+    average REAL,
+    spread REAL,
+    mean_deviation REAL
+);
+CREATE TABLE IF NOT EXISTS data_points (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    value INTEGER NOT NULL,
+    prob REAL NOT NULL,
+    accum REAL NOT NULL,
+    PRIMARY KEY (run_id, value)
+);
+"#;
+
+/// SQLiteWriter appends every invocation as a new batch to a database
+pub struct SQLiteWriter {
+    description: Option<String>,
+    conn: Connection,
+}
+impl SQLiteWriter {
+    /// opens the database and ensures the tables exist
+    pub fn open(path: &str, description: &Option<String>) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            description: description.clone(),
+            conn,
+        })
+    }
+
+    /// records all the data as a single batch
+    pub fn serialize_output(&mut self, data: &[TrollRecordable], config: &str) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO batches (started_at, config, description) VALUES (?1, ?2, ?3)",
+            params![unix_time(), config, self.description],
+        )?;
+        let batch_id = tx.last_insert_rowid();
+        for item in data {
+            let statistics = item.result.statistics();
+            tx.execute(
+                "INSERT INTO runs (batch_id, name, label, program, args, program_hash, samples, source, bin, average, spread, mean_deviation) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    batch_id,
+                    item.name,
+                    item.label,
+                    item.program.path,
+                    format_args(&item.program),
+                    item.program.hash,
+                    item.result.samples().map(|n| n as i64),
+                    item.program.source,
+                    item.program.bin,
+                    statistics.map(|s| s.average),
+                    statistics.map(|s| s.spread),
+                    statistics.map(|s| s.mean_deviation)
+                ],
+            )?;
+            let run_id = tx.last_insert_rowid();
+            for index in 0..item.result.len() {
+                let point = &item.result[index];
+                tx.execute(
+                    "INSERT INTO data_points (run_id, value, prob, accum) VALUES (?1, ?2, ?3, ?4)",
+                    params![run_id, outcome_value(&point.value), point.prob, point.accum],
+                )?;
+            }
+        }
+        tx.commit()
+    }
+}
+
+/// numbers are stored as integers, other outcomes as their text
+fn outcome_value(outcome: &Outcome) -> Value {
+    match outcome.integer() {
+        Option::Some(value) => Value::Integer(value),
+        Option::None => Value::Text(outcome.to_string()),
+    }
+}
+
+/// arguments are stored the same way they're passed to troll
+fn format_args(program: &ProgramInfo) -> String {
+    program
+        .args
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn unix_time() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(_) => 0,
+    }
+}
+
+#[test]
+fn test_batches() {
+    use super::super::super::exec::data::TrollData;
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+
+    let path = temp_dir().join(format!("trollrun-{}-history.db", ::std::process::id()));
+    let path = path.to_string_lossy().into_owned();
+    let _ = remove_file(&path);
+    let item = |name: &str, rolls: &[i64]| TrollRecordable {
+        name: name.to_string(),
+        label: None,
+        program: ProgramInfo {
+            path: format!("{}.t", name),
+            ..Default::default()
+        },
+        result: TrollData::from_samples(rolls),
+    };
+
+    let description = Some("first".to_string());
+    SQLiteWriter::open(&path, &description)
+        .unwrap()
+        .serialize_output(&[item("a", &[1, 2, 2])], "[runs]\na = \"a.t\"\n")
+        .unwrap();
+    SQLiteWriter::open(&path, &None)
+        .unwrap()
+        .serialize_output(&[item("a", &[1, 2, 2]), item("b", &[3])], "[runs]\n")
+        .unwrap();
+
+    let conn = Connection::open(&path).unwrap();
+    let batches: Vec<(i64, String, Option<String>)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, config, description FROM batches ORDER BY id")
+            .unwrap();
+        let rows = stmt
+            .query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap();
+        rows.collect::<Result<Vec<_>>>().unwrap()
+    };
+    assert_eq!(batches.len(), 2);
+    assert_eq!(batches[0].1, "[runs]\na = \"a.t\"\n");
+    assert_eq!(batches[0].2, Some("first".to_string()));
+    assert_eq!(batches[1].1, "[runs]\n");
+    assert_eq!(batches[1].2, None);
+
+    // batch, name, program, samples, average
+    type Run = (i64, String, String, Option<i64>, Option<f64>);
+    let runs: Vec<Run> = {
+        let mut stmt = conn
+            .prepare("SELECT batch_id, name, program, samples, average FROM runs ORDER BY id")
+            .unwrap();
+        let rows = stmt
+            .query_map(NO_PARAMS, |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .unwrap();
+        rows.collect::<Result<Vec<_>>>().unwrap()
+    };
+    assert_eq!(runs.len(), 3);
+    assert_eq!((runs[0].0, runs[0].1.as_str()), (batches[0].0, "a"));
+    assert_eq!((runs[1].0, runs[1].1.as_str()), (batches[1].0, "a"));
+    assert_eq!((runs[2].0, runs[2].1.as_str()), (batches[1].0, "b"));
+    assert_eq!(runs[2].2, "b.t");
+    assert_eq!(runs[0].3, Some(3));
+    assert_eq!(runs[2].4, Some(3.0));
+
+    // every outcome from 0 up to the largest roll
+    let points: Vec<(i64, f64, f64)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT value, prob, accum FROM data_points JOIN runs ON runs.id = run_id WHERE runs.name = 'b'",
+            )
+            .unwrap();
+        let rows = stmt
+            .query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap();
+        rows.collect::<Result<Vec<_>>>().unwrap()
+    };
+    assert_eq!(points.len(), 4);
+    assert_eq!(points[3], (3, 100.0, 100.0));
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM data_points", NO_PARAMS, |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(count, 3 + 3 + 4);
+    remove_file(&path).unwrap();
+}
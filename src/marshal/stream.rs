@@ -0,0 +1,79 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Result, Write};
+
+use super::super::serde_json::to_vec;
+
+use super::super::exec::runs::TrollRecordable;
+
+/// JSONLinesStream appends every run to a file as soon as it finishes.
+///
+/// Each run is one line of JSON (the same shape as the runs of the JSON output),
+/// so a crash or a slow run doesn't hold back the results which are done.
+pub struct JSONLinesStream {
+    file: File,
+}
+impl JSONLinesStream {
+    /// opens the stream, existing lines are kept
+    pub fn open(path: &str) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    /// writes the run as a single line, in a single write
+    pub fn record(&mut self, item: &TrollRecordable) -> Result<()> {
+        let mut line = to_vec(item)?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+}
+
+/// opens the stream (when one is given), failing to is fatal like any other output
+pub fn open_stream(path: &Option<String>) -> Option<JSONLinesStream> {
+    path.as_ref().map(|path| match JSONLinesStream::open(path) {
+        Err(e) => panic!("could not open file:'{}' error:'{:?}'", path, e),
+        Ok(stream) => stream,
+    })
+}
+
+/// records the run to the stream (if there is one), failures are only reported
+pub fn record(stream: &mut Option<JSONLinesStream>, item: &TrollRecordable) {
+    if let Option::Some(ref mut stream) = stream {
+        if let Err(e) = stream.record(item) {
+            eprintln!("failed to stream run:'{}' error:'{:?}'", item.name, e);
+        }
+    }
+}
+
+#[test]
+fn test_stream_appends_lines() {
+    use super::super::exec::data::TrollData;
+    use super::super::exec::runs::ProgramInfo;
+    use std::env::temp_dir;
+    use std::fs::{read_to_string, remove_file};
+
+    let path = temp_dir().join(format!("trollrun-{}-stream.jsonl", ::std::process::id()));
+    let path = path.to_string_lossy().into_owned();
+    let item = |name: &str| TrollRecordable {
+        name: name.to_string(),
+        label: None,
+        program: ProgramInfo::default(),
+        result: TrollData::from_samples(&[1, 2, 2]),
+    };
+
+    let _ = remove_file(&path);
+    JSONLinesStream::open(&path)
+        .unwrap()
+        .record(&item("first"))
+        .unwrap();
+    // reopening keeps what was already streamed
+    JSONLinesStream::open(&path)
+        .unwrap()
+        .record(&item("second"))
+        .unwrap();
+    let contents = read_to_string(&path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with(r#"{"name":"first""#));
+    assert!(lines[1].starts_with(r#"{"name":"second""#));
+    remove_file(&path).unwrap();
+}
@@ -0,0 +1,34 @@
+use super::super::csv::{Result, Writer};
+
+use super::super::exec::runs::RunReport;
+
+/// writes how long each run took, and the resources it used, as a CSV
+pub fn write_timings(path: &str, reports: &[RunReport]) -> Result<()> {
+    let mut writer = Writer::from_path(path)?;
+    writer.write_record([
+        "name",
+        "status",
+        "wall_seconds",
+        "user_seconds",
+        "system_seconds",
+        "max_rss_kib",
+        "mmap_retry",
+    ])?;
+    for report in reports {
+        writer.write_record(&[
+            report.name.clone(),
+            report.status.to_string(),
+            format!("{:.3}", report.timing.wall),
+            optional(report.timing.user_cpu.map(|x| format!("{:.3}", x))),
+            optional(report.timing.system_cpu.map(|x| format!("{:.3}", x))),
+            optional(report.timing.max_rss_kib.map(|x| x.to_string())),
+            report.retried.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn optional(arg: Option<String>) -> String {
+    arg.unwrap_or_default()
+}
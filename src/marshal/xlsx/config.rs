@@ -0,0 +1,19 @@
+use super::super::super::serde::Deserialize;
+
+use super::ser::XLSXWriter;
+
+#[derive(Clone, Debug, Deserialize, Default)]
+pub struct XLSXConfig {
+    pub path: String,
+    pub precision: Option<usize>,
+    pub flush_to_zero: Option<f64>,
+    pub value_header: Option<String>,
+    #[serde(default)]
+    pub order: Vec<String>,
+}
+impl XLSXConfig {
+    /// handles deserializing the input configuration junk
+    pub fn build_config(&self) -> XLSXWriter {
+        XLSXWriter::new(self)
+    }
+}
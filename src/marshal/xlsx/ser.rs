@@ -0,0 +1,207 @@
+use super::super::super::rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+use super::super::super::cli::StatBehavior;
+use super::super::super::exec::parser::Outcome;
+use super::super::super::exec::runs::TrollRecordable;
+use super::super::prep::{flush_to_zero, order_columns, preprocess, value_header};
+use super::config::XLSXConfig;
+
+/// XLSXWriter builds a workbook with the probabilities, cumulative values, and summary stats
+pub struct XLSXWriter {
+    path: String,
+    flush_to_zero: f64,
+    value_header: String,
+    order: Vec<String>,
+    header_format: Format,
+    number_format: Format,
+}
+impl XLSXWriter {
+    /// Create a new writer from the configuration
+    pub fn new(config: &XLSXConfig) -> Self {
+        // numbers are stored at full precision, this only changes how they're displayed
+        let number_format = match config.precision {
+            Option::Some(0) => Format::new().set_num_format("0"),
+            Option::Some(p) => Format::new().set_num_format(format!("0.{}", "0".repeat(p))),
+            Option::None => Format::new(),
+        };
+        Self {
+            path: config.path.clone(),
+            flush_to_zero: flush_to_zero(&config.flush_to_zero),
+            value_header: value_header(&config.value_header),
+            order: config.order.clone(),
+            header_format: Format::new().set_bold(),
+            number_format,
+        }
+    }
+
+    /// builds the workbook and saves it to disk
+    pub fn serialize_output(&mut self, data: Vec<TrollRecordable>) -> Result<(), XlsxError> {
+        self.build_workbook(data)?.save(&self.path)
+    }
+
+    /// builds the probability, cumulative, and summary sheets
+    fn build_workbook(&self, data: Vec<TrollRecordable>) -> Result<Workbook, XlsxError> {
+        // the summary describes the runs as troll reported them, before any flushing or padding
+        let mut untouched = data.clone();
+        order_columns(&self.order, &mut untouched);
+        let mut data = data;
+        let outcomes = preprocess(&self.flush_to_zero, &mut data);
+        order_columns(&self.order, &mut data);
+
+        let mut workbook = Workbook::new();
+        self.write_distribution(
+            workbook.add_worksheet().set_name("Probability")?,
+            &data,
+            &outcomes,
+            StatBehavior::RawStats,
+        )?;
+        self.write_distribution(
+            workbook.add_worksheet().set_name("Cumulative")?,
+            &data,
+            &outcomes,
+            StatBehavior::Accumulate,
+        )?;
+        self.write_summary(workbook.add_worksheet().set_name("Summary")?, &untouched)?;
+        Ok(workbook)
+    }
+
+    /// writes each run as a column, the first column holds the values
+    fn write_distribution(
+        &self,
+        sheet: &mut Worksheet,
+        data: &[TrollRecordable],
+        outcomes: &[Outcome],
+        behavior: StatBehavior,
+    ) -> Result<(), XlsxError> {
+        let columns: Vec<(String, Vec<f64>)> = data
+            .iter()
+            .cloned()
+            .flat_map(|item| item.split(behavior))
+            .collect();
+        sheet.write_string_with_format(0, 0, self.value_header.as_str(), &self.header_format)?;
+        for (column, (name, _)) in columns.iter().enumerate() {
+            sheet.write_string_with_format(
+                0,
+                (column + 1) as u16,
+                name.as_str(),
+                &self.header_format,
+            )?;
+        }
+        for (index, outcome) in outcomes.iter().enumerate() {
+            let row = (index + 1) as u32;
+            match outcome.integer() {
+                Option::Some(value) => sheet.write_number(row, 0, value as f64)?,
+                Option::None => sheet.write_string(row, 0, outcome.to_string())?,
+            };
+            for (column, (_, stats)) in columns.iter().enumerate() {
+                let mut value = stats[index];
+                if value <= self.flush_to_zero {
+                    value = 0.0;
+                }
+                sheet.write_number_with_format(
+                    row,
+                    (column + 1) as u16,
+                    value,
+                    &self.number_format,
+                )?;
+            }
+        }
+        sheet.set_freeze_panes(1, 1)?;
+        Ok(())
+    }
+
+    /// writes each run as a row of summary statistics
+    fn write_summary(
+        &self,
+        sheet: &mut Worksheet,
+        data: &[TrollRecordable],
+    ) -> Result<(), XlsxError> {
+        let headers = [
+            "Run",
+            "Average",
+            "Spread",
+            "Mean deviation",
+            "Minimum",
+            "Maximum",
+            "Samples",
+            "Executable",
+        ];
+        for (column, header) in headers.iter().enumerate() {
+            sheet.write_string_with_format(0, column as u16, *header, &self.header_format)?;
+        }
+        for (index, item) in data.iter().enumerate() {
+            let row = (index + 1) as u32;
+            sheet.write_string(row, 0, item.header())?;
+            if let Option::Some(statistics) = item.result.statistics() {
+                sheet.write_number_with_format(row, 1, statistics.average, &self.number_format)?;
+                sheet.write_number_with_format(row, 2, statistics.spread, &self.number_format)?;
+                sheet.write_number_with_format(
+                    row,
+                    3,
+                    statistics.mean_deviation,
+                    &self.number_format,
+                )?;
+            }
+            if let Option::Some(min) = item.result.min_value() {
+                sheet.write_number(row, 4, min as f64)?;
+            }
+            if let Option::Some(max) = item.result.max_value() {
+                sheet.write_number(row, 5, max as f64)?;
+            }
+            if let Option::Some(samples) = item.result.samples() {
+                sheet.write_number(row, 6, samples as f64)?;
+            }
+            if let Option::Some(ref bin) = item.program.bin {
+                sheet.write_string(row, 7, bin)?;
+            }
+        }
+        sheet.set_freeze_panes(1, 1)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_workbook() {
+    use super::super::super::exec::data::TrollData;
+    use super::super::super::exec::parser::TrollLine;
+    use super::super::super::exec::runs::ProgramInfo;
+    use std::env::temp_dir;
+    use std::fs::{metadata, remove_file};
+
+    let path = temp_dir().join(format!("trollrun-{}-workbook.xlsx", ::std::process::id()));
+    let data = vec![
+        TrollRecordable {
+            name: "sampled".to_string(),
+            label: None,
+            program: ProgramInfo::default(),
+            result: TrollData::from_samples(&[1, 2, 2, 3]),
+        },
+        TrollRecordable {
+            name: "text".to_string(),
+            label: None,
+            program: ProgramInfo::default(),
+            result: [r#""hit" : 25 100"#, r#""miss" : 75 75"#]
+                .iter()
+                .map(|line| TrollLine::new(line).unwrap())
+                .collect::<TrollData>(),
+        },
+    ];
+    let config = XLSXConfig {
+        path: path.to_str().unwrap().to_string(),
+        precision: Some(2),
+        ..Default::default()
+    };
+    let mut writer = XLSXWriter::new(&config);
+
+    let mut workbook = writer.build_workbook(data.clone()).unwrap();
+    let names: Vec<String> = workbook
+        .worksheets()
+        .iter()
+        .map(|sheet| sheet.name())
+        .collect();
+    assert_eq!(names, vec!["Probability", "Cumulative", "Summary"]);
+
+    writer.serialize_output(data).unwrap();
+    assert!(metadata(&path).unwrap().len() > 0);
+    remove_file(&path).unwrap();
+}
@@ -6,6 +6,10 @@ use super::toml::from_str;
 use super::exec::config::RunKind;
 use super::exec::runs::TrollRun;
 use super::marshal::csv::config::CSVConfig;
+use super::marshal::latex::config::LaTeXConfig;
+use super::marshal::output::OutputConfig;
+use super::marshal::sqlite::config::SQLiteConfig;
+use super::marshal::xlsx::config::XLSXConfig;
 
 /// Top level configuration format
 #[derive(Clone, Deserialize, Debug)]
@@ -15,7 +19,18 @@ pub struct ConfigFormat {
     #[serde(default)]
     pub csv: Option<CSVConfig>,
     #[serde(default)]
+    pub sqlite: Option<SQLiteConfig>,
+    #[serde(default)]
+    pub xlsx: Option<XLSXConfig>,
+    #[serde(default)]
+    pub latex: Option<LaTeXConfig>,
+    #[serde(default)]
+    pub output: Vec<OutputConfig>,
+    #[serde(default)]
     pub runs: BTreeMap<String, RunKind>,
+    /// the text the config was parsed from
+    #[serde(skip)]
+    pub text: String,
 }
 impl ConfigFormat {
     // load a config from the command line interface
@@ -29,35 +44,87 @@ impl ConfigFormat {
                 "failed to parse config:'{}' error:'{:?}'",
                 &path, e
             )),
-            Ok(config) => Ok(config),
+            Ok(mut parsed) => {
+                parsed.text = config;
+                parsed.validate().map(|_| parsed)
+            }
         }
     }
 
-    /// builds all the items that need to be ran
-    pub fn build_runs(&self) -> Vec<TrollRun> {
-        let troll_path = self.get_troll_path();
+    /// catches mistakes serde can't, like runs without a program
+    fn validate(&self) -> Result<(), String> {
+        let troll = self.troll.clone().unwrap_or_default();
+        for (name, run) in self.runs.iter() {
+            if let RunKind::ComplexRun(ref complex) = run {
+                complex.validate(name, &troll)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// builds all the items that need to be ran, `strict` fails the ones whose
+    /// output doesn't pass the sanity checks
+    pub fn build_runs(&self, strict: bool) -> Vec<TrollRun> {
+        let troll = self.troll.clone().unwrap_or_default();
+        self.runs
+            .iter()
+            .map(|(name, run)| run.into_exec(name, &troll, strict))
+            .collect()
+    }
+
+    /// the fingerprint of every run, see `RunKind::fingerprint`
+    pub fn fingerprints(&self) -> BTreeMap<String, u64> {
+        let troll = self.troll.clone().unwrap_or_default();
         self.runs
             .iter()
-            .map(|(name, run)| run.into_exec(name, &troll_path))
+            .map(|(name, run)| (name.clone(), run.fingerprint(&troll)))
             .collect()
     }
 
-    /// returns the path to the damn executable
-    fn get_troll_path<'a>(&'a self) -> Option<String> {
-        match &self.troll {
-            &Option::Some(ref cfg) => match &cfg.path {
-                &Option::Some(ref path) => Some(path.clone()),
-                _ => None,
-            },
-            _ => None,
+    /// every output the results should be written to.
+    ///
+    /// The single-format sections (`[csv]`, `[sqlite]`, etc.) come first, then
+    /// every `[[output]]`. When nothing is configured the CSV goes to stdout.
+    pub fn outputs(&self) -> Vec<OutputConfig> {
+        let mut outputs = Vec::new();
+        if let Option::Some(ref cfg) = self.csv {
+            outputs.push(OutputConfig::Csv(cfg.clone()));
+        }
+        if let Option::Some(ref cfg) = self.sqlite {
+            outputs.push(OutputConfig::Sqlite(cfg.clone()));
+        }
+        if let Option::Some(ref cfg) = self.xlsx {
+            outputs.push(OutputConfig::Xlsx(cfg.clone()));
+        }
+        if let Option::Some(ref cfg) = self.latex {
+            outputs.push(OutputConfig::Latex(cfg.clone()));
+        }
+        outputs.extend(self.output.iter().cloned());
+        if outputs.is_empty() {
+            outputs.push(OutputConfig::Csv(CSVConfig::default()));
         }
+        outputs
     }
 }
 
 /// How do you want to run troll
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, Default)]
 pub struct TrollConfig {
     pub path: Option<String>,
+    /// limit (in MiB) on the address space of every troll process
+    pub max_memory: Option<u64>,
+    /// apply the mmap workaround to every run, unless the run says otherwise
+    pub mmap_override: Option<bool>,
+    /// environment variables set for every run
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// start every run with an empty environment
+    pub env_clear: Option<bool>,
+    /// the working directory of every run
+    pub cwd: Option<String>,
+    /// named troll executables, runs pick one with `bin`
+    #[serde(default)]
+    pub bins: BTreeMap<String, String>,
 }
 
 #[test]
@@ -76,3 +143,38 @@ reroll_one_dda = { path = "lol_dda.t", args = { rr1 = 1 } }
 
     let _output = from_str::<ConfigFormat>(dut).unwrap();
 }
+
+#[test]
+fn test_multiple_outputs() {
+    let dut = r#"
+[csv]
+path = "results.csv"
+
+[[output]]
+format = "json"
+path = "results.json"
+
+[[output]]
+format = "markdown"
+precision = 2
+
+[runs]
+base_dda = "lol_dda.t"
+"#;
+
+    let output = from_str::<ConfigFormat>(dut).unwrap();
+    let outputs = output.outputs();
+    assert_eq!(outputs.len(), 3);
+    match &outputs[0] {
+        OutputConfig::Csv(cfg) => assert_eq!(cfg.path, Some("results.csv".to_string())),
+        x => panic!("expected csv found:'{:?}'", x),
+    };
+    match &outputs[1] {
+        OutputConfig::Json(cfg) => assert_eq!(cfg.path, Some("results.json".to_string())),
+        x => panic!("expected json found:'{:?}'", x),
+    };
+    match &outputs[2] {
+        OutputConfig::Markdown(cfg) => assert_eq!(cfg.precision, Some(2)),
+        x => panic!("expected markdown found:'{:?}'", x),
+    };
+}
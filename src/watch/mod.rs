@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+use super::cli::AppConfig;
+use super::exec::exec::run_program;
+use super::exec::interrupt::exit_code;
+use super::exec::runs::{TrollRecordable, TrollRun};
+use super::marshal::output::{write_all, write_partial, OutputWriter};
+use super::marshal::stream::{open_stream, record, JSONLinesStream};
+use super::marshal::timings::write_timings;
+use super::unmarshal::ConfigFormat;
+
+pub mod notify;
+use self::notify::Watcher;
+
+/// Cached is the last result of a run, & the fingerprint it was ran with
+struct Cached {
+    fingerprint: u64,
+    result: Option<TrollRecordable>,
+}
+
+/// watch re-runs troll whenever the config, or one of the programs, changes.
+///
+/// Only runs whose fingerprint changed are executed again, the results of
+/// the others are kept from the previous round.
+pub fn watch(flags: &AppConfig) -> ! {
+    let mut cache: BTreeMap<String, Cached> = BTreeMap::new();
+    let mut stream = open_stream(&flags.stream);
+    loop {
+        let config = match ConfigFormat::new(&flags.file_path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{}", e);
+                wait_for(&[PathBuf::from(&flags.file_path)]);
+                continue;
+            }
+        };
+        let troll = config.troll.clone().unwrap_or_default();
+
+        // watch before running, so saves made while troll runs are not missed
+        let mut files = vec![PathBuf::from(&flags.file_path)];
+        for run in config.runs.values() {
+            files.extend(run.watched_files(&troll));
+        }
+        // a bad output path would panic when the outputs are opened, like without
+        // --watch it is reported before running, & fixing the config is waited for
+        let problems: Vec<String> = config
+            .outputs()
+            .iter()
+            .filter_map(|output| output.check().err())
+            .collect();
+        if !problems.is_empty() {
+            for problem in problems {
+                eprintln!("{}", problem);
+            }
+            wait_for(&files);
+            continue;
+        }
+        let watcher = match Watcher::new(&files) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("failed to watch files error:'{:?}'", e);
+                ::std::process::exit(1);
+            }
+        };
+
+        let fingerprints = config.fingerprints();
+        cache.retain(|name, _| fingerprints.contains_key(name));
+        let runs: Vec<TrollRun> = config
+            .build_runs(flags.strict)
+            .into_iter()
+            .filter(|run| {
+                cache.get(&run.name).map(|cached| cached.fingerprint)
+                    != fingerprints.get(&run.name).cloned()
+            })
+            .collect();
+
+        eprintln!(
+            "trollrun: running {} of {} runs",
+            runs.len(),
+            fingerprints.len()
+        );
+        execute(flags, &config, runs, &fingerprints, &mut cache, &mut stream);
+        eprintln!(
+            "trollrun: watching {} files for changes, Ctrl-C to stop",
+            files.len()
+        );
+        if let Err(e) = watcher.wait() {
+            stop(e);
+        }
+    }
+}
+
+/// runs the changed runs (if any), & rewrites the outputs with every cached result
+fn execute(
+    flags: &AppConfig,
+    config: &ConfigFormat,
+    runs: Vec<TrollRun>,
+    fingerprints: &BTreeMap<String, u64>,
+    cache: &mut BTreeMap<String, Cached>,
+    stream: &mut Option<JSONLinesStream>,
+) {
+    // the outputs are rewritten even when nothing has to run, their config may have changed
+    let mut interrupted = false;
+    if !runs.is_empty() {
+        let names: Vec<String> = runs.iter().map(|run| run.name.clone()).collect();
+        let batch = run_program(runs, !flags.quiet, &mut |item| record(stream, item));
+        interrupted = batch.interrupted;
+        if let Option::Some(ref path) = flags.timings {
+            if let Err(e) = write_timings(path, &batch.reports) {
+                eprintln!("failed to write timings:'{}' error:'{:?}'", path, e);
+            }
+        }
+        let mut results: BTreeMap<String, TrollRecordable> = batch
+            .data
+            .into_iter()
+            .map(|item| (item.name.clone(), item))
+            .collect();
+        for name in names {
+            let cached = Cached {
+                fingerprint: fingerprints[&name],
+                result: results.remove(&name),
+            };
+            cache.insert(name, cached);
+        }
+    }
+
+    // the cache is ordered by name, the same order runs are built in
+    let data: Vec<TrollRecordable> = cache
+        .values()
+        .filter_map(|cached| cached.result.clone())
+        .collect();
+    if interrupted {
+        // the same as a batch without --watch, the real outputs are left alone
+        write_partial(&config.outputs(), &data, flags.behavior, &config.text);
+        eprintln!(
+            "trollrun: interrupted, the {} completed runs were written with a `.partial` suffix",
+            data.len()
+        );
+        ::std::process::exit(exit_code());
+    }
+    let outputs: Vec<OutputWriter> = config
+        .outputs()
+        .iter()
+        .map(|cfg| cfg.build_config())
+        .collect();
+    write_all(outputs, &data, flags.behavior, &config.text);
+}
+
+/// blocks until one of the files changes
+fn wait_for(files: &[PathBuf]) {
+    let result = Watcher::new(files).and_then(|watcher| watcher.wait());
+    if let Err(e) = result {
+        stop(e);
+    }
+}
+
+/// watching ends with Ctrl-C, anything else is an error
+fn stop(e: Error) -> ! {
+    // every round rewrites the outputs before watching, so there is nothing partial left to write
+    if e.kind() == ErrorKind::Interrupted {
+        ::std::process::exit(exit_code());
+    }
+    eprintln!("failed to watch files error:'{:?}'", e);
+    ::std::process::exit(1);
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+use super::super::exec::interrupt::interrupted;
+
+/// the directory a file is in, & its name within it.
+///
+/// Directories are watched instead of files, editors often save by
+/// writing a new file & renaming it over the old one.
+fn split(path: &Path) -> (PathBuf, OsString) {
+    let dir = match path.parent() {
+        Option::Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    (dir, name)
+}
+
+/// groups the files by the directory they're in
+fn by_directory(paths: &[PathBuf]) -> BTreeMap<PathBuf, Vec<OsString>> {
+    let mut dirs: BTreeMap<PathBuf, Vec<OsString>> = BTreeMap::new();
+    for path in paths {
+        let (dir, name) = split(path);
+        dirs.entry(dir).or_default().push(name);
+    }
+    dirs
+}
+
+/// Watcher blocks until one of the files it was given changes.
+///
+/// Changes made after the watcher is created but before `wait` is called
+/// are not missed, so it can be set up before running troll.
+#[cfg(target_os = "linux")]
+pub struct Watcher {
+    fd: i32,
+    /// which files are interesting in each watched directory
+    dirs: BTreeMap<i32, Vec<OsString>>,
+}
+
+#[cfg(target_os = "linux")]
+impl Watcher {
+    pub fn new(paths: &[PathBuf]) -> Result<Watcher> {
+        use super::super::libc;
+        use std::ffi::CString;
+        use std::io::Error;
+        use std::os::unix::ffi::OsStrExt;
+
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        let mut watcher = Watcher {
+            fd,
+            dirs: BTreeMap::new(),
+        };
+        let mask = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE | libc::IN_DELETE;
+        for (dir, names) in by_directory(paths) {
+            let cdir = CString::new(dir.as_os_str().as_bytes())?;
+            let wd = unsafe { libc::inotify_add_watch(fd, cdir.as_ptr(), mask) };
+            if wd < 0 {
+                return Err(Error::last_os_error());
+            }
+            watcher.dirs.entry(wd).or_default().extend(names);
+        }
+        Ok(watcher)
+    }
+
+    /// blocks until a watched file changes.
+    ///
+    /// Saving a file often shows up as a burst of events, these are collected
+    /// until things are quiet for a moment.
+    pub fn wait(&self) -> Result<()> {
+        loop {
+            stop_if_interrupted()?;
+            if self.readable(CHECK_MS) && self.read_events()? {
+                break;
+            }
+        }
+        while self.readable(DEBOUNCE_MS) {
+            self.read_events()?;
+        }
+        Ok(())
+    }
+
+    /// reads the pending events, returns if any of them were about a watched file
+    fn read_events(&self) -> Result<bool> {
+        use super::super::libc;
+        use std::ffi::OsStr;
+        use std::io::{Error, ErrorKind};
+        use std::mem::size_of;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut buffer = [0u8; 4096];
+        let len = unsafe { libc::read(self.fd, buffer.as_mut_ptr() as *mut libc::c_void, 4096) };
+        if len < 0 {
+            let err = Error::last_os_error();
+            return match err.kind() {
+                ErrorKind::Interrupted => Ok(false),
+                _ => Err(err),
+            };
+        }
+        let len = len as usize;
+        let header = size_of::<libc::inotify_event>();
+        let mut changed = false;
+        let mut offset = 0;
+        while offset + header <= len {
+            let event = unsafe {
+                (buffer.as_ptr().add(offset) as *const libc::inotify_event).read_unaligned()
+            };
+            let start = offset + header;
+            let end = (start + event.len as usize).min(len);
+            let name = &buffer[start..end];
+            let name = match name.iter().position(|b| *b == 0) {
+                Option::Some(nul) => &name[..nul],
+                Option::None => name,
+            };
+            if let Option::Some(names) = self.dirs.get(&event.wd) {
+                changed |= names
+                    .iter()
+                    .any(|n| n.as_os_str() == OsStr::from_bytes(name));
+            }
+            offset = end;
+        }
+        Ok(changed)
+    }
+
+    /// waits (up to the timeout) for more events
+    fn readable(&self, timeout_ms: i32) -> bool {
+        use super::super::libc;
+
+        let mut fds = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut fds, 1, timeout_ms) > 0 }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        unsafe {
+            super::super::libc::close(self.fd);
+        }
+    }
+}
+
+/// Watcher blocks until one of the files it was given changes.
+///
+/// Without inotify the modification times are polled.
+#[cfg(not(target_os = "linux"))]
+pub struct Watcher {
+    files: Vec<(PathBuf, Option<::std::time::SystemTime>)>,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Watcher {
+    pub fn new(paths: &[PathBuf]) -> Result<Watcher> {
+        let files = paths
+            .iter()
+            .map(|path| (path.clone(), modified(path)))
+            .collect();
+        Ok(Watcher { files })
+    }
+
+    /// blocks until a watched file changes
+    pub fn wait(&self) -> Result<()> {
+        use std::thread::sleep;
+        use std::time::Duration;
+
+        loop {
+            stop_if_interrupted()?;
+            sleep(Duration::from_millis(POLL_MS));
+            if self
+                .files
+                .iter()
+                .any(|(path, last)| modified(path) != *last)
+            {
+                sleep(Duration::from_millis(DEBOUNCE_MS as u64));
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn modified(path: &Path) -> Option<::std::time::SystemTime> {
+    ::std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// watching stops when trollrun is interrupted
+fn stop_if_interrupted() -> Result<()> {
+    use std::io::{Error, ErrorKind};
+
+    match interrupted() {
+        Option::None => Ok(()),
+        Option::Some(_) => Err(Error::new(
+            ErrorKind::Interrupted,
+            "trollrun was interrupted",
+        )),
+    }
+}
+
+/// how often inotify is checked for events, in between checking for Ctrl-C
+#[cfg(target_os = "linux")]
+const CHECK_MS: i32 = 250;
+
+/// how long things have to be quiet before a change is acted on
+const DEBOUNCE_MS: i32 = 100;
+
+/// how often modification times are checked without inotify
+#[cfg(not(target_os = "linux"))]
+const POLL_MS: u64 = 500;
+
+#[test]
+fn test_by_directory() {
+    let paths = vec![
+        PathBuf::from("config.toml"),
+        PathBuf::from("/srv/troll/a.t"),
+        PathBuf::from("/srv/troll/b.t"),
+    ];
+    let dirs = by_directory(&paths);
+    assert_eq!(dirs.len(), 2);
+    assert_eq!(dirs[Path::new(".")], vec![OsString::from("config.toml")]);
+    assert_eq!(dirs[Path::new("/srv/troll")].len(), 2);
+}